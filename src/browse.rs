@@ -0,0 +1,260 @@
+//! `--tui`: browse the scanned rows in the terminal before anything is written.
+//!
+//! The table shows the `--preview` columns; `/` filters it as you type, `1`–`9`
+//! sort by a column, and the pane below lists every tag of the selected file.
+//! Leaving with `w` hands the rows still shown back for writing.
+
+use std::{
+    cmp::Ordering,
+    io,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Cell, Paragraph, Row as TableRow, Table, TableState},
+    DefaultTerminal, Frame,
+};
+
+use crate::{
+    preview::{COLUMNS, MAX_COLUMN_WIDTH},
+    Row,
+};
+
+/// Height of the tag pane, borders included.
+const DETAIL_HEIGHT: u16 = 12;
+/// Rows moved by PageUp / PageDown.
+const PAGE: usize = 20;
+
+/// Show `rows` until the user leaves. `Some` holds the rows left after
+/// filtering, in the order shown, when they asked for them to be written (`w`);
+/// `None` means quit without writing (`q`, Ctrl-C).
+pub fn browse<'a>(rows: &[&'a Row]) -> io::Result<Option<Vec<&'a Row>>> {
+    let mut browser = Browser::new(rows);
+    let mut terminal = ratatui::init(); // restores the terminal on panic too
+    let picked = browser.run(&mut terminal);
+    ratatui::restore();
+    picked
+}
+
+struct Browser<'a> {
+    rows: Vec<&'a Row>,
+    cells: Vec<Vec<String>>,     // per row, one per preview column
+    filter: String,              // "canon", or "model:canon" for one column
+    typing: bool,                // keys go to the filter line
+    sort: Option<(usize, bool)>, // column, and whether descending
+    view: Vec<usize>,            // rows passing the filter, in display order
+    table: TableState,           // selection and scroll position
+    message: Option<String>,     // last problem, e.g. a file that wouldn't open
+}
+
+impl<'a> Browser<'a> {
+    fn new(rows: &[&'a Row]) -> Browser<'a> {
+        let cells = rows.iter().map(|row| COLUMNS.iter().map(|c| (c.cell)(row)).collect()).collect();
+        let mut browser = Browser {
+            rows: rows.to_vec(),
+            cells,
+            filter: String::new(),
+            typing: false,
+            sort: None,
+            view: Vec::new(),
+            table: TableState::default(),
+            message: None,
+        };
+        browser.refresh();
+        browser
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<Option<Vec<&'a Row>>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else { continue };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                return Ok(None);
+            }
+            if self.typing {
+                self.type_filter(key);
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') => return Ok(None),
+                KeyCode::Char('w') => return Ok(Some(self.view.iter().map(|&i| self.rows[i]).collect())),
+                KeyCode::Char('/') => self.typing = true,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.refresh();
+                }
+                KeyCode::Char('e') => self.open_selected(),
+                KeyCode::Char(c @ '1'..='9') => self.sort_by(c as usize - '1' as usize),
+                KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+                KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+                KeyCode::PageDown => self.move_by(PAGE as isize),
+                KeyCode::PageUp => self.move_by(-(PAGE as isize)),
+                KeyCode::Home | KeyCode::Char('g') => self.table.select(Some(0)),
+                KeyCode::End | KeyCode::Char('G') => self.table.select(Some(self.view.len().saturating_sub(1))),
+                _ => {}
+            }
+        }
+    }
+
+    fn type_filter(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => self.typing = false,
+            KeyCode::Esc => {
+                self.typing = false;
+                self.filter.clear();
+            }
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Char(c) => self.filter.push(c),
+            _ => return,
+        }
+        self.refresh();
+    }
+
+    /// Same column again flips the direction.
+    fn sort_by(&mut self, column: usize) {
+        if column >= COLUMNS.len() {
+            return;
+        }
+        self.sort = match self.sort {
+            Some((current, descending)) if current == column => Some((column, !descending)),
+            _ => Some((column, false)),
+        };
+        self.refresh();
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let Some(last) = self.view.len().checked_sub(1) else { return };
+        let at = self.table.selected().unwrap_or(0);
+        self.table.select(Some(at.saturating_add_signed(delta).min(last)));
+    }
+
+    /// Recompute the view after the filter or the sort changed.
+    fn refresh(&mut self) {
+        let needle = self.filter.to_lowercase();
+        // "model:canon" looks in one column, anything else in all of them
+        let (column, needle) = match needle.split_once(':') {
+            Some((name, rest)) if COLUMNS.iter().any(|c| c.header == name) => {
+                (COLUMNS.iter().position(|c| c.header == name), rest.to_string())
+            }
+            _ => (None, needle),
+        };
+        let matches = |cells: &[String]| match column {
+            Some(i) => cells[i].to_lowercase().contains(&needle),
+            None => cells.iter().any(|cell| cell.to_lowercase().contains(&needle)),
+        };
+        self.view = (0..self.rows.len()).filter(|&i| matches(&self.cells[i])).collect();
+        if let Some((column, descending)) = self.sort {
+            self.view.sort_by(|&a, &b| {
+                let order = compare_cells(&self.cells[a][column], &self.cells[b][column]);
+                if descending { order.reverse() } else { order }
+            });
+        }
+        self.table.select((!self.view.is_empty()).then_some(0));
+    }
+
+    fn selected(&self) -> Option<&'a Row> {
+        let i = *self.view.get(self.table.selected()?)?;
+        Some(self.rows[i])
+    }
+
+    fn open_selected(&mut self) {
+        let Some(row) = self.selected() else { return };
+        self.message = open_file(&row.path).err().map(|e| format!("can't open {}: {}", row.path.display(), e));
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [table_area, detail_area, status_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(DETAIL_HEIGHT), Constraint::Length(1)])
+                .areas(frame.area());
+
+        let header = TableRow::new(COLUMNS.iter().enumerate().map(|(i, c)| {
+            let arrow = match self.sort {
+                Some((column, false)) if column == i => " ↑",
+                Some((column, true)) if column == i => " ↓",
+                _ => "",
+            };
+            Cell::from(format!("{} {}{}", i + 1, c.header, arrow)) // the number sorts by it
+        }))
+        .style(Style::new().add_modifier(Modifier::BOLD));
+        let lines = self.view.iter().map(|&i| TableRow::new(self.cells[i].iter().map(|cell| Cell::from(cell.as_str()))));
+        // the path takes what the other columns leave, those get their natural width
+        let widths = (0..COLUMNS.len()).map(|i| {
+            if i == 0 {
+                return Constraint::Fill(1);
+            }
+            let widest = self.view.iter().map(|&row| self.cells[row][i].chars().count()).max().unwrap_or(0);
+            Constraint::Length(widest.max(COLUMNS[i].header.len() + 2).min(MAX_COLUMN_WIDTH) as u16)
+        });
+        let table = Table::new(lines, widths)
+            .header(header)
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        let (title, detail) = match self.selected() {
+            Some(row) => {
+                let mut detail: Vec<Line> = Vec::new();
+                if let Some(error) = &row.error {
+                    detail.push(Line::from(format!("error: {error}")));
+                }
+                detail.extend(row.tags.iter().map(|tag| Line::from(format!("{}: {}", tag.name, tag.value))));
+                (row.path.to_string_lossy().into_owned(), detail)
+            }
+            None => ("no row selected".to_string(), Vec::new()),
+        };
+        frame.render_widget(Paragraph::new(detail).block(Block::bordered().title(title)), detail_area);
+
+        let status = if self.typing {
+            format!("/{}█  (Enter keeps the filter, Esc drops it)", self.filter)
+        } else {
+            let filter = if self.filter.is_empty() { String::new() } else { format!(" matching \"{}\"", self.filter) };
+            let message = self.message.as_deref().map(|m| format!("  {m}")).unwrap_or_default();
+            format!(
+                "{} of {} rows{}  / filter  1-9 sort  e open  w write these and quit  q quit{}",
+                self.view.len(),
+                self.rows.len(),
+                filter,
+                message
+            )
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+}
+
+/// Numbers as numbers (f_number, iso, …), anything else as text; blanks first.
+fn compare_cells(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// Hand `path` to the desktop's default viewer, without waiting for it.
+fn open_file(path: &Path) -> io::Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let mut command = Command::new("xdg-open");
+    command
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(drop)
+}
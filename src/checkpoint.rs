@@ -0,0 +1,127 @@
+//! `--checkpoint` / `--resume`: how far an interrupted run got, so the next one
+//! can go on from there instead of starting over.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::output::path_hash;
+
+/// The state of a checkpointed run, as saved after each batch of files.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Checkpoint {
+    pub options: String,   // the run's settings in config syntax (see `ScanOptions::checkpoint_options`)
+    pub files: usize,      // how many files the run set out to process
+    pub file_list: String, // hash of their paths, to notice files that came or went
+    pub completed: usize,  // the first this many of them are in the output
+    pub output: PathBuf,   // the output file the rows are appended to
+    pub output_bytes: u64, // its length once those rows were written
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> Result<Checkpoint, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("can't read checkpoint {}: {}", path.display(), e))?;
+        serde_json::from_str(&text).map_err(|e| format!("{} is not a checkpoint: {}", path.display(), e))
+    }
+
+    /// Save to `path` atomically: written next to it, then renamed over it, so
+    /// a crash half-way leaves the previous checkpoint intact.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".tmp");
+        let tmp = path.with_file_name(name);
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+
+    /// Why this checkpoint's run can't be continued with `options` over
+    /// `files`, if it can't: the settings that changed, or the file list.
+    pub fn mismatch(&self, options: &str, files: &[PathBuf]) -> Option<String> {
+        let before: Vec<&str> = self.options.lines().collect();
+        let now: Vec<&str> = options.lines().collect();
+        let mut changed: Vec<&str> = before
+            .iter()
+            .filter(|line| !now.contains(line))
+            .chain(now.iter().filter(|line| !before.contains(line)))
+            .filter_map(|line| line.split(" = ").next())
+            .collect();
+        changed.sort_unstable();
+        changed.dedup();
+        if !changed.is_empty() {
+            return Some(format!("the options differ from the checkpointed run: {}", changed.join(", ")));
+        }
+        if self.files != files.len() || self.file_list != file_list_hash(files) {
+            return Some(format!(
+                "the files to process changed since the checkpoint ({} then, {} now)",
+                self.files,
+                files.len()
+            ));
+        }
+        None
+    }
+}
+
+/// One hash over every path in `files`, in order.
+pub fn file_list_hash(files: &[PathBuf]) -> String {
+    let joined: PathBuf = files.iter().map(|f| f.to_string_lossy()).collect::<Vec<_>>().join("\n").into();
+    path_hash(&joined)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::ScratchDir;
+
+    fn checkpoint(files: &[PathBuf]) -> Checkpoint {
+        Checkpoint {
+            options: "recursive = true\nmake = \"Canon\"".into(),
+            files: files.len(),
+            file_list: file_list_hash(files),
+            completed: 1,
+            output: "exif_output.csv".into(),
+            output_bytes: 512,
+        }
+    }
+
+    #[test]
+    fn save_round_trips_through_load() {
+        let dir = ScratchDir::new("checkpoint");
+        let path = dir.path().join("run.json");
+        let files = vec![PathBuf::from("./a.jpg"), PathBuf::from("./b.jpg")];
+        checkpoint(&files).save(&path).unwrap();
+        let loaded = Checkpoint::load(&path).unwrap();
+        assert_eq!(loaded.options, "recursive = true\nmake = \"Canon\"");
+        assert_eq!((loaded.files, loaded.completed, loaded.output_bytes), (2, 1, 512));
+        assert_eq!(loaded.output, PathBuf::from("exif_output.csv"));
+        assert_eq!(loaded.mismatch("recursive = true\nmake = \"Canon\"", &files), None);
+        assert!(!dir.path().join("run.json.tmp").exists());
+    }
+
+    #[test]
+    fn mismatch_names_changed_options() {
+        let files = vec![PathBuf::from("./a.jpg")];
+        let message = checkpoint(&files).mismatch("recursive = false\nmake = \"Canon\"", &files).unwrap();
+        assert_eq!(message, "the options differ from the checkpointed run: recursive");
+    }
+
+    #[test]
+    fn mismatch_notices_a_changed_file_list() {
+        let files = vec![PathBuf::from("./a.jpg"), PathBuf::from("./b.jpg")];
+        let options = "recursive = true\nmake = \"Canon\"";
+        let renamed = vec![PathBuf::from("./a.jpg"), PathBuf::from("./c.jpg")];
+        assert_eq!(
+            checkpoint(&files).mismatch(options, &renamed).unwrap(),
+            "the files to process changed since the checkpoint (2 then, 2 now)"
+        );
+        assert_eq!(
+            checkpoint(&files).mismatch(options, &files[..1]).unwrap(),
+            "the files to process changed since the checkpoint (2 then, 1 now)"
+        );
+    }
+}
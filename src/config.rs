@@ -0,0 +1,600 @@
+//! Run settings, shared by the command line and the `exif_tool.toml` config file.
+//!
+//! Config keys are the long option names without the dashes (`max-size = "200M"`,
+//! `format = "html"`, `require-tags = ["Artist"]`); the binary reads the file
+//! first and lets command-line flags override it.
+
+use std::{error::Error, fs, path::Path, path::PathBuf, thread};
+
+use chrono::format::StrftimeItems;
+use rexif::IfdKind;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    fields,
+    geofence::Geofence,
+    organize,
+    output::{Field, Format, PathStyle},
+    Column, ExtractOptions, Sanitize, BASE_COLUMNS,
+};
+
+/// Config file picked up from the working directory when `--config` isn't given.
+pub const DEFAULT_CONFIG_FILE: &str = "exif_tool.toml";
+
+/// What `--group-by` splits the output on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    Camera, // the EXIF Model tag; files without one go to `unknown`
+}
+
+/// Zone of the run stamp (`--tz`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Zone {
+    #[default]
+    Local,
+    Utc,
+}
+
+/// Which files are parsed first (`--order-by`); the output follows unless `--sort-by` is given.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderBy {
+    #[default]
+    Path,
+    Mtime, // files whose modification time can't be read go last
+    Size,
+}
+
+/// Order of the output rows (`--sort-by`); rows without the key go last.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    #[default]
+    Path,
+    Date,  // EXIF DateTimeOriginal
+    Model, // camera Model tag
+    Size,  // file size in bytes
+}
+
+/// Direction of `--order-by` (`--order`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Order {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Everything one run is told. The default is the drop-in-and-run behaviour:
+/// scan the current folder and write `exif_output.csv`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ScanOptions {
+    pub organize: Option<PathBuf>,    // --organize / --organize-into <dest>: file images into dated folders
+    #[serde(rename = "by", deserialize_with = "de_layout")]
+    pub layout: Option<String>,       // --by year/month|<strftime>: the dated sub-folders (default YYYY/MM)
+    pub require_exif_date: bool,      // --require-exif-date: no mtime fallback, undated files stay put
+    pub dry_run: bool,                // --dry-run: print the organize / touch plan, leave the images alone
+    #[serde(rename = "move")]
+    pub move_files: bool,             // --move: rename into place instead of copying
+    pub touch: bool,                  // --touch: set each image's mtime to its EXIF capture time
+    pub error_log: Option<PathBuf>,   // --error-log <file>: one CSV row per failed file
+    #[serde(deserialize_with = "de_size")]
+    pub max_size: u64,                // --max-size <size>: skip larger files (0 = no limit)
+    pub limit: Option<usize>,         // --limit <n>: only the first n files (after sorting)
+    pub sample: Option<usize>,        // --sample <n>: a random n of the files found
+    pub seed: Option<u64>,            // --seed <n>: the --sample draw to use, for repeating a run
+    pub order_by: OrderBy,            // --order-by path|mtime|size: which files are parsed first
+    pub order: Order,                 // --order asc|desc: direction of --order-by
+    pub count_only: bool,             // --count-only: print how many files would be processed, then exit
+    pub recursive: bool,              // --recursive: scan sub-folders too
+    pub max_depth: Option<usize>,     // --max-depth <n>: with --recursive, at most n levels down
+    pub files_from: Option<PathBuf>,  // --files-from <list|->: process exactly the listed files, no scan
+    pub null_separated: bool,         // -0: the --files-from entries end in NUL, not newline
+    pub follow_symlinks: bool,        // --follow-symlinks: read symlinked files too (deduplicated by target)
+    pub sniff: bool,                  // --sniff: pick files by content, not by extension
+    pub raw: bool,                    // --raw: camera RAW files too (needs the `raw` feature)
+    pub exclude_ext: Vec<String>,     // --exclude-ext tif,tiff: leave out files with these extensions
+    pub threads: usize,               // --threads <n>: extraction threads (0 = one per core)
+    #[serde(rename = "max-open-files")]
+    pub max_open: Option<usize>,      // --max-open-files <n>: cap on images open at once
+    pub long: bool,                   // --long: one path,tag,value line per tag
+    pub absolute_paths: bool,         // --absolute-paths: canonicalize paths in the output
+    pub retries: u32,                 // --retries <n>: re-read after IO errors (0 = no retry)
+    pub pretty_exposure: bool,        // --pretty-exposure: aperture_display / shutter_display columns too
+    pub raw_tags: bool,               // --raw-tags: each tag's hex ID, data type and unconverted value too
+    pub raw_values: bool,             // --raw-values: control characters in values as \x07 escapes, not spaces
+    pub fail_fast: bool,              // --fail-fast: stop at the first file that fails, exit non-zero
+    #[serde(with = "ifd_name")]
+    pub prefer_ifd: Option<IfdKind>,  // --prefer-ifd <ifd>: which copy of a repeated tag to keep
+    pub tz: Zone,                     // --tz utc|local: zone of the csv_created_at stamp
+    #[serde(deserialize_with = "de_time_format")]
+    pub time_format: Option<String>,  // --time-format <strftime>: instead of RFC 3339
+    pub group_by: Option<GroupBy>,    // --group-by camera: one output file per group
+    pub dedup_output: bool,           // --dedup-output: one row per file, however often it was listed
+    pub sort_by: SortBy,              // --sort-by path|date|model|size: order of the output rows
+    #[serde(rename = "assume-offset", deserialize_with = "de_offset")]
+    pub offset: Option<String>,       // --assume-offset ±HH:MM: UTC offset of files without an offset tag
+    pub format: Format,               // --format csv|tsv|jsonl|kml|html|md|xml|parquet
+    pub output: Option<PathBuf>,      // --output <file>: instead of exif_output.<ext>; a .gz name compresses
+    pub compress: bool,               // --compress: gzip the output
+    pub chunk_rows: Option<usize>,    // --chunk-rows <n>: numbered output parts of at most n rows each
+    pub columns: Vec<String>,         // --columns a,b,c: exactly these output columns, in this order
+    pub other_tags: bool,             // --other-tags: with --columns, the tags not picked still follow
+    pub stdout: bool,                 // --stdout: write the data to standard output
+    pub json_errors: bool,            // --json-errors: errors on stderr as JSON objects, not prose
+    pub on_complete: Option<String>,  // --on-complete <command>: run per output file, `{}` = its path
+    pub bom: bool,                    // --bom: UTF-8 byte-order mark at the start of the CSV/TSV
+    pub max_cell_width: usize,        // --max-cell-width <n>: cut Markdown cells (0 = no limit)
+    pub preview: usize,               // --preview <n>: print the first n records as a table (0 = none)
+    pub tui: bool,                    // --tui: browse the rows in the terminal first (needs the `tui` feature)
+    pub hash_paths: bool,             // --hash-paths: hashed paths in the output, file names kept
+    pub hash_only: bool,              // --hash-only: with --hash-paths, drop the file names too
+    pub path_map: Option<PathBuf>,    // --path-map <file>: hash,path pairs for de-anonymizing
+    pub kml: Option<PathBuf>,         // --kml <file>: Google Earth placemarks too, whatever --format is
+    pub manifest: Option<PathBuf>,    // --manifest <file>: status of every processed file (.json or CSV)
+    pub rollup: Option<PathBuf>,      // --rollup <file>: one CSV line of totals per folder
+    pub rollup_depth: Option<usize>,  // --rollup-depth <n>: count files toward the n folders above theirs too
+    pub require_tags: Vec<String>,    // --require-tags a,b,c: flag files lacking any of them
+    pub only_missing: bool,           // --only-missing: output only the files flagged above
+    pub min_tags: usize,              // --min-tags <n>: leave out files with fewer EXIF tags (0 = keep all)
+    #[serde(serialize_with = "area_text::serialize", deserialize_with = "area_text::within")]
+    pub within: Option<Geofence>,     // --within <lat>,<lon>,<radius>: only photos taken in that circle
+    #[serde(serialize_with = "area_text::serialize", deserialize_with = "area_text::bbox")]
+    pub bbox: Option<Geofence>,       // --bbox <lat1>,<lon1>,<lat2>,<lon2>: only photos in that rectangle
+    pub include_no_gps: bool,         // --include-no-gps: keep photos without GPS despite the two above
+    #[serde(deserialize_with = "de_duration")]
+    pub date_drift: Option<u64>,      // --date-drift <duration>: flag files whose mtime is this far from the EXIF date
+    pub checkpoint: Option<PathBuf>,  // --checkpoint <file>: record progress, for --resume
+    pub resume: Option<PathBuf>,      // --resume <file>: go on from that checkpoint, appending to its output
+    #[serde(rename = "checkpoint-every")]
+    pub every: Option<usize>,         // --checkpoint-every <n>: files between checkpoints (default 1000)
+    #[serde(rename = "checkpoint-interval", deserialize_with = "de_duration")]
+    pub interval: Option<u64>,        // --checkpoint-interval <duration>: also save once this many seconds passed
+    pub timing: bool,                 // --timing: per-phase and per-file times on stderr
+    pub timing_json: Option<PathBuf>, // --timing-json <file>: the same as JSON
+}
+
+impl ScanOptions {
+    /// Read a config file. Keys this version doesn't know are returned (as
+    /// dotted paths) so the caller can warn about them; they are not an error.
+    pub fn from_file(path: &Path) -> Result<(ScanOptions, Vec<String>), Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        let (opts, unknown) = ScanOptions::from_toml(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok((opts, unknown))
+    }
+
+    /// [`ScanOptions::from_file`] for text already in memory.
+    pub fn from_toml(text: &str) -> Result<(ScanOptions, Vec<String>), toml::de::Error> {
+        let mut unknown = Vec::new();
+        let opts = serde_ignored::deserialize(toml::Deserializer::new(text), |key| unknown.push(key.to_string()))?;
+        Ok((opts, unknown))
+    }
+
+    /// The effective settings in config-file syntax (`--print-config`).
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("every option has a TOML representation")
+    }
+
+    /// The settings a `--resume` must share with the run it continues, in
+    /// config-file syntax: everything but the checkpoint options themselves and
+    /// the knobs that only change speed or reporting.
+    pub fn checkpoint_options(&self) -> String {
+        ScanOptions {
+            checkpoint: None,
+            resume: None,
+            every: None,
+            interval: None,
+            threads: 0,
+            max_open: None,
+            timing: false,
+            timing_json: None,
+            preview: 0,
+            ..self.clone()
+        }
+        .to_toml()
+    }
+
+    /// Reject combinations that make no sense, whichever source they came from.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.raw && !cfg!(feature = "raw") {
+            return Err("--raw needs a build with RAW support: cargo build --features raw".into());
+        }
+        if self.format == Format::Parquet && !cfg!(feature = "parquet") {
+            return Err("--format parquet needs a build with Parquet support: cargo build --features parquet".into());
+        }
+        if self.format == Format::Parquet && self.compressed() {
+            return Err("Parquet files are compressed already; drop --compress / the .gz name".into());
+        }
+        if self.checkpoint.is_some() || self.resume.is_some() {
+            if !matches!(self.format, Format::Csv | Format::Tsv | Format::Jsonl) {
+                return Err("--checkpoint / --resume need --format csv, tsv or jsonl, which can be appended to".into());
+            }
+            // each batch is appended as it's done, so nothing may reorder, split or revisit the rows
+            let conflicts = [
+                (self.stdout, "--stdout"),
+                (self.compressed(), "--compress"),
+                (self.group_by.is_some(), "--group-by"),
+                (self.chunk_rows.is_some(), "--chunk-rows"),
+                (self.sort_by != SortBy::Path, "--sort-by"),
+                (self.dedup_output, "--dedup-output"),
+                (self.organize.is_some(), "--organize"),
+                (self.tui, "--tui"),
+                (self.touch, "--touch"),
+                (self.kml.is_some(), "--kml"),
+                (self.manifest.is_some(), "--manifest"),
+                (self.rollup.is_some(), "--rollup"),
+                (self.path_map.is_some(), "--path-map"),
+                (self.preview > 0, "--preview"),
+                (self.sample.is_some() && self.seed.is_none(), "--sample without --seed"),
+            ];
+            if let Some((_, option)) = conflicts.iter().find(|(set, _)| *set) {
+                return Err(format!("--checkpoint / --resume can't be combined with {option}"));
+            }
+        }
+        if self.every == Some(0) {
+            return Err("--checkpoint-every must be a positive number of files".into());
+        }
+        if self.interval == Some(0) {
+            return Err("--checkpoint-interval must be a positive duration".into());
+        }
+        if self.interval.is_some() && self.checkpoint.is_none() && self.resume.is_none() {
+            return Err("--checkpoint-interval only makes sense together with --checkpoint or --resume".into());
+        }
+        if self.tui && !cfg!(feature = "tui") {
+            return Err("--tui needs a build with the terminal browser: cargo build --features tui".into());
+        }
+        if self.tui && self.stdout {
+            return Err("--tui can't be combined with --stdout".into());
+        }
+        if self.limit == Some(0) {
+            return Err("--limit must be a positive number of files".into());
+        }
+        if self.sample == Some(0) {
+            return Err("--sample must be a positive number of files".into());
+        }
+        if self.seed.is_some() && self.sample.is_none() {
+            return Err("--seed only applies to --sample".into());
+        }
+        if self.long && !matches!(self.format, Format::Csv | Format::Tsv) {
+            return Err("--long only applies to --format csv or tsv".into());
+        }
+        if self.long && !self.columns.is_empty() {
+            return Err("--columns can't be combined with --long".into());
+        }
+        let custom = self.extract_options().custom_columns();
+        if self.fields(&custom).is_some_and(|fields| fields.is_empty()) {
+            return Err(format!("none of the --columns names is known; available: {}", Field::available(&custom)));
+        }
+        if self.other_tags && self.columns.is_empty() {
+            return Err("--other-tags only makes sense together with --columns".into());
+        }
+        if self.bom && !matches!(self.format, Format::Csv | Format::Tsv) {
+            return Err("--bom only applies to --format csv or tsv".into());
+        }
+        if self.output.is_some() && (self.stdout || self.group_by.is_some()) {
+            return Err("--output names one file, so it can't be combined with --stdout or --group-by".into());
+        }
+        if self.max_open == Some(0) {
+            return Err("--max-open-files must be at least 1".into());
+        }
+        if self.null_separated && self.files_from.is_none() {
+            return Err("-0 only makes sense together with --files-from".into());
+        }
+        if self.files_from.is_some() && (self.recursive || self.sniff || !self.exclude_ext.is_empty()) {
+            return Err(
+                "--files-from already names the files, so it can't be combined with --recursive, --sniff or --exclude-ext".into(),
+            );
+        }
+        if self.rollup_depth.is_some() && self.rollup.is_none() {
+            return Err("--rollup-depth only makes sense together with --rollup".into());
+        }
+        if self.max_depth.is_some() && !self.recursive {
+            return Err("--max-depth only makes sense together with --recursive".into());
+        }
+        if self.chunk_rows == Some(0) {
+            return Err("--chunk-rows must be a positive number of rows".into());
+        }
+        if self.chunk_rows.is_some() && (self.stdout || self.group_by.is_some()) {
+            return Err("--chunk-rows splits one output file, so it can't be combined with --stdout or --group-by".into());
+        }
+        if let Some(command) = &self.on_complete {
+            if command.trim().is_empty() {
+                return Err("--on-complete needs a command".into());
+            }
+            if self.stdout {
+                return Err("--on-complete gets the output file's path, so it can't be combined with --stdout".into());
+            }
+        }
+        if self.within.is_some() && self.bbox.is_some() {
+            return Err("--within and --bbox each draw the area, so only one of them can be given".into());
+        }
+        if self.include_no_gps && self.geofence().is_none() {
+            return Err("--include-no-gps only makes sense together with --within or --bbox".into());
+        }
+        if self.stdout && self.group_by.is_some() {
+            return Err("--group-by writes several files, so it can't be combined with --stdout".into());
+        }
+        if self.only_missing && self.require_tags.is_empty() {
+            return Err("--only-missing only makes sense together with --require-tags".into());
+        }
+        for (set, flag) in [(self.hash_only, "--hash-only"), (self.path_map.is_some(), "--path-map")] {
+            if set && !self.hash_paths {
+                return Err(format!("{flag} only makes sense together with --hash-paths"));
+            }
+        }
+        for (set, flag) in [
+            (self.move_files, "--move"),
+            (self.layout.is_some(), "--by"),
+            (self.require_exif_date, "--require-exif-date"),
+        ] {
+            if set && self.organize.is_none() {
+                return Err(format!("{flag} only makes sense together with --organize <dest>"));
+            }
+        }
+        if self.dry_run && self.organize.is_none() && !self.touch {
+            return Err("--dry-run only makes sense together with --organize <dest> or --touch".into());
+        }
+        Ok(())
+    }
+
+    /// Size of the worker pool (0 = rayon's default, one per core). Each worker
+    /// has at most one image open at a time, so `--max-open-files` caps it.
+    pub fn worker_threads(&self) -> usize {
+        let Some(cap) = self.max_open else {
+            return self.threads;
+        };
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        threads.min(cap)
+    }
+
+    /// Fixed CSV columns for this run, when `--columns` doesn't pick them.
+    pub fn fixed_columns(&self) -> Vec<Column> {
+        let mut columns = BASE_COLUMNS.to_vec();
+        if self.pretty_exposure {
+            columns.extend([Column::ApertureDisplay, Column::ShutterDisplay]);
+        }
+        if self.sniff {
+            columns.extend([Column::ClaimedExt, Column::DetectedType, Column::TypeMismatch]);
+        }
+        if self.organize.is_some() {
+            columns.extend([Column::OrganizedPath, Column::OrganizeAction]);
+        }
+        if self.touch {
+            columns.extend([Column::MtimeBefore, Column::MtimeAfter, Column::TouchAction]);
+        }
+        if !self.require_tags.is_empty() {
+            columns.push(Column::MissingTags);
+        }
+        if self.date_drift.is_some() {
+            columns.extend([Column::DateDriftSeconds, Column::DateDrift]);
+        }
+        columns
+    }
+
+    /// The `--columns` selection, resolved against the fixed, `custom` and tag
+    /// names, without the unknown ones; `None` when every column is wanted.
+    pub fn fields(&self, custom: &[String]) -> Option<Vec<Field>> {
+        if self.columns.is_empty() {
+            return None;
+        }
+        Some(Field::parse_list(&self.columns, custom).0)
+    }
+
+    /// Whether the rows are written in the order the files were parsed
+    /// (`--order-by` / `--order` without `--sort-by`) rather than sorted by path.
+    pub fn parse_order_output(&self) -> bool {
+        self.sort_by == SortBy::Path && (self.order_by != OrderBy::Path || self.order != Order::Asc)
+    }
+
+    /// Whether the output is gzipped: `--compress`, or an `--output` name ending in `.gz`.
+    pub fn compressed(&self) -> bool {
+        let gz_name = self.output.as_ref().is_some_and(|p| p.extension().is_some_and(|e| e == "gz"));
+        self.compress || gz_name
+    }
+
+    /// How control characters in values are cleaned for this run's format.
+    pub fn sanitize(&self) -> Sanitize {
+        Sanitize {
+            escape: self.raw_values,
+            keep_newlines: self.format.keeps_newlines(),
+        }
+    }
+
+    /// The `--within` / `--bbox` area, if one was given.
+    pub fn geofence(&self) -> Option<Geofence> {
+        self.within.or(self.bbox)
+    }
+
+    /// How paths are written to the output.
+    pub fn path_style(&self) -> PathStyle {
+        match (self.hash_paths, self.hash_only) {
+            (false, _) => PathStyle::Full,
+            (true, false) => PathStyle::HashAndName,
+            (true, true) => PathStyle::Hash,
+        }
+    }
+
+    /// Settings for the extraction step (no custom columns registered).
+    pub fn extract_options(&self) -> ExtractOptions {
+        ExtractOptions {
+            retries: self.retries,
+            prefer_ifd: self.prefer_ifd,
+            ..ExtractOptions::default()
+        }
+    }
+}
+
+/// Parse a byte count with an optional K/M/G/T suffix (powers of 1024), e.g. `200M`.
+/// A trailing `B` / `iB` and a fractional amount are accepted too: `50MB`, `1.5GiB`.
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid size '{text}' (expected e.g. 500K, 200M, 2G)");
+    let upper = text.trim().to_ascii_uppercase();
+    let number = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (digits, multiplier) = match number.chars().last() {
+        Some('K') => (&number[..number.len() - 1], 1u64 << 10),
+        Some('M') => (&number[..number.len() - 1], 1 << 20),
+        Some('G') => (&number[..number.len() - 1], 1 << 30),
+        Some('T') => (&number[..number.len() - 1], 1 << 40),
+        _ => (number, 1),
+    };
+    if let Ok(n) = digits.parse::<u64>() {
+        return n.checked_mul(multiplier).ok_or_else(invalid);
+    }
+    match digits.parse::<f64>() {
+        Ok(n) if n.is_finite() && n >= 0.0 => Ok((n * multiplier as f64).round() as u64),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parse a `--date-drift` threshold or a `--checkpoint-interval` into seconds: a number with an optional
+/// `s`, `m`, `h` or `d` suffix (`90`, `30m`, `2h`, `1d`).
+pub fn parse_duration(text: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid duration '{text}' (expected e.g. 90s, 30m, 2h, 1d)");
+    let text = text.trim();
+    let (digits, unit) = match text.chars().last() {
+        Some('s') => (&text[..text.len() - 1], 1),
+        Some('m') => (&text[..text.len() - 1], 60),
+        Some('h') => (&text[..text.len() - 1], 60 * 60),
+        Some('d') => (&text[..text.len() - 1], 24 * 60 * 60),
+        _ => (text, 1),
+    };
+    let n: u64 = digits.parse().map_err(|_| invalid())?;
+    n.checked_mul(unit).ok_or_else(invalid)
+}
+
+/// Turn a `--by` value into a strftime folder template: one of the named
+/// layouts `year`, `year/month`, `year/month/day`, or a template such as `%Y/%m-%d`.
+pub fn parse_layout(by: &str) -> Result<String, String> {
+    let template = match by {
+        "year" => "%Y",
+        "year/month" => organize::DEFAULT_LAYOUT,
+        "year/month/day" => "%Y/%m/%d",
+        custom if custom.contains('%') => custom,
+        _ => return Err(format!("--by must be year, year/month, year/month/day or a %-template, not '{by}'")),
+    };
+    // a leading separator or `..` would escape the destination folder
+    let escapes = template.starts_with(['/', '\\']) || template.split(['/', '\\']).any(|part| part == "..");
+    if escapes || StrftimeItems::new(template).parse().is_err() {
+        return Err(format!("invalid --by template '{by}'"));
+    }
+    Ok(template.to_string())
+}
+
+/// Check a `--time-format` strftime pattern.
+pub fn parse_time_format(format: &str) -> Result<String, String> {
+    match StrftimeItems::new(format).parse() {
+        Ok(_) => Ok(format.to_string()),
+        Err(_) => Err(format!("invalid --time-format pattern '{format}'")),
+    }
+}
+
+/// Check an `--assume-offset` value: `+02:00`, `-05:30`, …
+pub fn parse_assume_offset(offset: &str) -> Result<String, String> {
+    match fields::parse_offset(offset) {
+        Some(_) => Ok(offset.to_string()),
+        None => Err(format!("invalid --assume-offset '{offset}' (expected ±HH:MM, e.g. +02:00)")),
+    }
+}
+
+/// `--prefer-ifd` names for rexif's IFD kinds.
+const IFD_NAMES: [(&str, IfdKind); 6] = [
+    ("ifd0", IfdKind::Ifd0),
+    ("ifd1", IfdKind::Ifd1),
+    ("exif", IfdKind::Exif),
+    ("gps", IfdKind::Gps),
+    ("makernote", IfdKind::Makernote),
+    ("interop", IfdKind::Interoperability),
+];
+
+/// Parse a `--prefer-ifd` value.
+pub fn parse_ifd(name: &str) -> Result<IfdKind, String> {
+    IFD_NAMES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|&(_, kind)| kind)
+        .ok_or_else(|| "--prefer-ifd must be one of: ifd0, ifd1, exif, gps, makernote, interop".to_string())
+}
+
+/// `max-size` may be a plain number of bytes or a string such as `"200M"`.
+fn de_size<'de, D: Deserializer<'de>>(d: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+    match Size::deserialize(d)? {
+        Size::Bytes(n) => Ok(n),
+        Size::Text(text) => parse_size(&text).map_err(serde::de::Error::custom),
+    }
+}
+
+/// `date-drift` may be a plain number of seconds or a string such as `"2h"`.
+fn de_duration<'de, D: Deserializer<'de>>(d: D) -> Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Duration {
+        Seconds(u64),
+        Text(String),
+    }
+    match Duration::deserialize(d)? {
+        Duration::Seconds(n) => Ok(Some(n)),
+        Duration::Text(text) => parse_duration(&text).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+fn de_layout<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    let by = String::deserialize(d)?;
+    parse_layout(&by).map(Some).map_err(serde::de::Error::custom)
+}
+
+fn de_offset<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    let offset = String::deserialize(d)?;
+    parse_assume_offset(&offset).map(Some).map_err(serde::de::Error::custom)
+}
+
+fn de_time_format<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    let format = String::deserialize(d)?;
+    parse_time_format(&format).map(Some).map_err(serde::de::Error::custom)
+}
+
+
+/// `within` / `bbox` as the text `--within` / `--bbox` take.
+mod area_text {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(area: &Option<Geofence>, s: S) -> Result<S::Ok, S::Error> {
+        area.map(|area| area.to_string()).serialize(s)
+    }
+
+    pub fn within<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Geofence>, D::Error> {
+        let within = String::deserialize(d)?;
+        Geofence::parse_within(&within).map(Some).map_err(serde::de::Error::custom)
+    }
+
+    pub fn bbox<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Geofence>, D::Error> {
+        let bbox = String::deserialize(d)?;
+        Geofence::parse_bbox(&bbox).map(Some).map_err(serde::de::Error::custom)
+    }
+}
+
+/// `prefer-ifd` as its `--prefer-ifd` name.
+mod ifd_name {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(kind: &Option<IfdKind>, s: S) -> Result<S::Ok, S::Error> {
+        let name = IFD_NAMES.iter().find(|(_, k)| Some(*k) == *kind).map(|(n, _)| *n);
+        name.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<IfdKind>, D::Error> {
+        let name = String::deserialize(d)?;
+        parse_ifd(&name).map(Some).map_err(serde::de::Error::custom)
+    }
+}
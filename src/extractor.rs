@@ -0,0 +1,25 @@
+//! Hook for project-specific columns computed from each file's EXIF.
+
+use std::{fmt, path::Path};
+
+use rexif::ExifData;
+
+/// A custom output column. Register implementations in
+/// [`ExtractOptions::extractors`](crate::ExtractOptions::extractors); each one is
+/// asked for its value after the built-in fields, and its answer becomes a column
+/// named [`name`](ColumnExtractor::name) after the fixed ones.
+///
+/// Extraction runs on several threads, hence `Send + Sync`.
+pub trait ColumnExtractor: Send + Sync {
+    /// Column header; should be unique among the registered extractors.
+    fn name(&self) -> &str;
+
+    /// The cell for one file, `None` for a blank cell.
+    fn value(&self, exif: &ExifData, path: &Path) -> Option<String>;
+}
+
+impl fmt::Debug for dyn ColumnExtractor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ColumnExtractor({:?})", self.name())
+    }
+}
@@ -0,0 +1,334 @@
+//! Typed, human-friendly fields derived from raw EXIF tag values.
+
+use chrono::FixedOffset;
+use rexif::{ExifEntry, ExifTag, IfdKind, TagValue};
+
+/// Readable meaning of an EXIF `Orientation` value (1–8), `None` for anything else.
+pub fn orientation_label(value: u16) -> Option<&'static str> {
+    Some(match value {
+        1 => "Normal",
+        2 => "Mirror horizontal",
+        3 => "Rotate 180",
+        4 => "Mirror vertical",
+        5 => "Mirror horizontal and rotate 270 CW",
+        6 => "Rotate 90 CW",
+        7 => "Mirror horizontal and rotate 90 CW",
+        8 => "Rotate 270 CW",
+        _ => return None,
+    })
+}
+
+/// Exposure settings as numbers, for sorting and charting. Absent tags stay `None`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Exposure {
+    pub f_number: Option<f64>,
+    pub exposure_seconds: Option<f64>,
+    pub exposure_display: Option<String>, // the stored fraction, e.g. "1/250"
+    pub iso: Option<u32>,
+    pub focal_length_mm: Option<f64>,
+    pub focal_length_35mm: Option<f64>,
+}
+
+/// First rational of a tag as `(numerator, denominator)`; signed values are widened.
+pub fn first_rational(value: &TagValue) -> Option<(i64, i64)> {
+    match value {
+        TagValue::URational(v) => v.first().map(|r| (i64::from(r.numerator), i64::from(r.denominator))),
+        TagValue::IRational(v) => v.first().map(|r| (i64::from(r.numerator), i64::from(r.denominator))),
+        _ => None,
+    }
+}
+
+/// First value of a numeric tag as a float; a rational with a zero denominator is `None`.
+pub fn first_f64(value: &TagValue) -> Option<f64> {
+    if let Some((num, den)) = first_rational(value) {
+        return (den != 0).then(|| num as f64 / den as f64);
+    }
+    value.to_f64(0).filter(|v| v.is_finite())
+}
+
+/// The fraction as stored, `"1/250"`, or just the numerator when the denominator is 1.
+pub fn rational_display(value: &TagValue) -> Option<String> {
+    match first_rational(value)? {
+        (_, 0) => None,
+        (num, 1) => Some(num.to_string()),
+        (num, den) => Some(format!("{num}/{den}")),
+    }
+}
+
+impl Exposure {
+    /// Pick up the exposure-related tags; call once per EXIF entry.
+    pub fn add(&mut self, tag: ExifTag, value: &TagValue) {
+        match tag {
+            ExifTag::FNumber => self.f_number = first_f64(value),
+            ExifTag::ExposureTime => {
+                self.exposure_seconds = first_f64(value);
+                self.exposure_display = rational_display(value);
+            }
+            ExifTag::ISOSpeedRatings => self.iso = value.to_i64(0).and_then(|v| u32::try_from(v).ok()),
+            ExifTag::FocalLength => self.focal_length_mm = first_f64(value),
+            ExifTag::FocalLengthIn35mmFilm => self.focal_length_35mm = first_f64(value),
+            _ => {}
+        }
+    }
+
+    /// Aperture the way a lens barrel shows it: `f/2.8`, `f/8`.
+    pub fn aperture_label(&self) -> Option<String> {
+        let f = self.f_number.filter(|&f| f > 0.0)?;
+        let rounded = format!("{f:.1}");
+        Some(format!("f/{}", rounded.trim_end_matches(".0")))
+    }
+
+    /// Shutter speed the way a camera shows it: `1/250` below a second, `2s` or
+    /// `1.3s` from a second up, whatever fraction was stored.
+    pub fn shutter_label(&self) -> Option<String> {
+        let seconds = self.exposure_seconds.filter(|&s| s > 0.0)?;
+        if seconds < 1.0 {
+            return Some(format!("1/{}", (1.0 / seconds).round()));
+        }
+        let rounded = format!("{seconds:.1}");
+        Some(format!("{}s", rounded.trim_end_matches(".0")))
+    }
+}
+
+/// Degrees/minutes/seconds rationals (GPSLatitude, GPSLongitude) as decimal degrees.
+/// Any zero denominator makes the whole value unusable.
+pub fn dms_to_decimal(value: &TagValue) -> Option<f64> {
+    let TagValue::URational(parts) = value else {
+        return None;
+    };
+    let mut decimal = 0.0;
+    for (part, scale) in parts.iter().zip([1.0, 60.0, 3600.0]) {
+        if part.denominator == 0 {
+            return None;
+        }
+        decimal += f64::from(part.numerator) / f64::from(part.denominator) / scale;
+    }
+    (!parts.is_empty()).then_some(decimal)
+}
+
+/// Position collected from the GPS IFD, in decimal degrees (south and west negative).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GpsParts {
+    latitude: Option<f64>,
+    latitude_ref: Option<char>,
+    longitude: Option<f64>,
+    longitude_ref: Option<char>,
+}
+
+impl GpsParts {
+    /// Pick up the GPS position tags; call once per EXIF entry.
+    pub fn add(&mut self, tag: ExifTag, value: &TagValue) {
+        let hemisphere = || match value {
+            TagValue::Ascii(s) => s.trim().chars().next().map(|c| c.to_ascii_uppercase()),
+            _ => None,
+        };
+        match tag {
+            ExifTag::GPSLatitude => self.latitude = dms_to_decimal(value),
+            ExifTag::GPSLatitudeRef => self.latitude_ref = hemisphere(),
+            ExifTag::GPSLongitude => self.longitude = dms_to_decimal(value),
+            ExifTag::GPSLongitudeRef => self.longitude_ref = hemisphere(),
+            _ => {}
+        }
+    }
+
+    /// `(latitude, longitude)` when both are present and on the globe.
+    pub fn position(&self) -> Option<(f64, f64)> {
+        let mut lat = self.latitude?;
+        let mut lon = self.longitude?;
+        if self.latitude_ref == Some('S') {
+            lat = -lat;
+        }
+        if self.longitude_ref == Some('W') {
+            lon = -lon;
+        }
+        ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)).then_some((lat, lon))
+    }
+}
+
+/// A UTC offset as EXIF writes it (OffsetTimeOriginal): `+02:00`, `-05:30`.
+/// Anything else, including the blank `"   :  "` some cameras store, is `None`.
+pub fn parse_offset(text: &str) -> Option<FixedOffset> {
+    let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    let (sign, rest) = match text.as_bytes().first()? {
+        b'+' => (1, &text[1..]),
+        b'-' => (-1, &text[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = rest.split_once(':')?;
+    if hours.len() != 2 || minutes.len() != 2 {
+        return None;
+    }
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    // real zones run from -12:00 to +14:00
+    if hours > 14 || minutes > 59 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Numeric IDs of the size tags, none of which rexif has a name for.
+const IMAGE_WIDTH: u16 = 0x0100;
+const IMAGE_LENGTH: u16 = 0x0101;
+const PIXEL_X_DIMENSION: u16 = 0xa002;
+const PIXEL_Y_DIMENSION: u16 = 0xa003;
+
+/// Pixel size from the tags. The Exif IFD's PixelX/YDimension describe the
+/// stored image; IFD0's ImageWidth/ImageLength are the fallback (IFD1's belong
+/// to the thumbnail, so they are ignored).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dimensions {
+    pixel_x: Option<u32>,
+    pixel_y: Option<u32>,
+    image_width: Option<u32>,
+    image_length: Option<u32>,
+}
+
+impl Dimensions {
+    /// Pick up the size tags; call once per EXIF entry.
+    pub fn add(&mut self, entry: &ExifEntry) {
+        let pixels = || entry.value.to_i64(0).and_then(|v| u32::try_from(v).ok()).filter(|&v| v > 0);
+        if entry.tag != ExifTag::UnknownToMe {
+            return;
+        }
+        match (entry.ifd.tag, entry.kind) {
+            (PIXEL_X_DIMENSION, IfdKind::Exif) => self.pixel_x = pixels(),
+            (PIXEL_Y_DIMENSION, IfdKind::Exif) => self.pixel_y = pixels(),
+            (IMAGE_WIDTH, IfdKind::Ifd0) => self.image_width = pixels(),
+            (IMAGE_LENGTH, IfdKind::Ifd0) => self.image_length = pixels(),
+            _ => {}
+        }
+    }
+
+    /// `(width, height)` from whichever pair of tags is complete.
+    pub fn size(&self) -> Option<(u32, u32)> {
+        match (self.pixel_x, self.pixel_y) {
+            (Some(w), Some(h)) => Some((w, h)),
+            _ => Some((self.image_width?, self.image_length?)),
+        }
+    }
+}
+
+/// Trimmed text of an ASCII tag; `None` when it is blank or not text at all.
+pub fn ascii(value: &TagValue) -> Option<String> {
+    match value {
+        TagValue::Ascii(s) => {
+            let s = s.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+            (!s.is_empty()).then(|| s.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Numeric ID of the EXIF 2.3 LensSerialNumber tag, which rexif has no name for.
+const LENS_SERIAL_NUMBER: u16 = 0xa435;
+
+/// Lens identification from the Exif IFD. Absent tags stay `None`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lens {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+}
+
+impl Lens {
+    /// Pick up the lens tags; call once per EXIF entry.
+    pub fn add(&mut self, entry: &ExifEntry) {
+        match entry.tag {
+            ExifTag::LensMake => self.make = ascii(&entry.value),
+            ExifTag::LensModel => self.model = ascii(&entry.value),
+            ExifTag::UnknownToMe if entry.ifd.tag == LENS_SERIAL_NUMBER => self.serial = ascii(&entry.value),
+            _ => {}
+        }
+    }
+}
+
+/// Numeric ID of the TIFF/EP ProcessingSoftware tag, which rexif has no name for.
+pub const PROCESSING_SOFTWARE: u16 = 0x000b;
+
+/// Editors whose name turns up in Software; matched case-insensitively.
+const EDITORS: [&str; 16] = [
+    "lightroom", "photoshop", "gimp", "capture one", "darktable", "rawtherapee", "snapseed", "affinity",
+    "pixelmator", "luminar", "dxo", "acdsee", "paint.net", "picasa", "digikam", "photos",
+];
+
+/// Guess whether a Software value means the image was edited: `false` for
+/// camera firmware, `true` for an editor or anything else. Firmware says little
+/// more than a version ("1.0.2", "Ver.1.01", "ILCE-7M3 v3.01", "HDR+ 1.0.3") or
+/// is a bare build code ("G991BXXU5CVF3"), so this is a heuristic, not proof.
+pub fn looks_edited(software: &str) -> bool {
+    let lower = software.to_ascii_lowercase();
+    if EDITORS.iter().any(|editor| lower.contains(editor)) {
+        return true;
+    }
+    let version = |word: &str| {
+        let number = word
+            .trim_start_matches("firmware")
+            .trim_start_matches("version")
+            .trim_start_matches("ver")
+            .trim_start_matches('v')
+            .trim_start_matches(['.', ':']);
+        number.starts_with(|c: char| c.is_ascii_digit()) && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+    };
+    let build_code = !lower.contains(' ')
+        && lower.chars().any(|c| c.is_ascii_digit())
+        && lower.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    !(build_code || lower.split_whitespace().any(version))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use rexif::{ExifTag, IfdKind, TagValue};
+
+    use super::orientation_label;
+    use crate::{testutil::entry, Column, Row};
+
+    /// The `orientation` and `needs_rotation` cells of a file whose Orientation tag holds `value`.
+    fn cells(value: TagValue) -> (String, String) {
+        let tag = entry(ExifTag::Orientation, IfdKind::Ifd0, value, "");
+        let row = Row::from_exif(PathBuf::from("a.jpg"), "image/jpeg", &[&tag], None);
+        (row.cell(Column::Orientation), row.cell(Column::NeedsRotation))
+    }
+
+    #[test]
+    fn labels_all_eight_orientations() {
+        let expected = [
+            "Normal",
+            "Mirror horizontal",
+            "Rotate 180",
+            "Mirror vertical",
+            "Mirror horizontal and rotate 270 CW",
+            "Rotate 90 CW",
+            "Mirror horizontal and rotate 90 CW",
+            "Rotate 270 CW",
+        ];
+        for (value, label) in (1..=8).zip(expected) {
+            assert_eq!(orientation_label(value), Some(label), "orientation {value}");
+        }
+    }
+
+    #[test]
+    fn out_of_range_orientation_has_no_label() {
+        for value in [0, 9, u16::MAX] {
+            assert_eq!(orientation_label(value), None, "orientation {value}");
+            assert_eq!(cells(TagValue::U16(vec![value])), (String::new(), String::new()));
+        }
+    }
+
+    #[test]
+    fn non_numeric_orientation_leaves_cells_blank() {
+        assert_eq!(cells(TagValue::Ascii("six".into())), (String::new(), String::new()));
+        assert_eq!(cells(TagValue::U16(Vec::new())), (String::new(), String::new()));
+    }
+
+    #[test]
+    fn only_normal_needs_no_rotation() {
+        for value in 1..=8 {
+            let (label, needs_rotation) = cells(TagValue::U16(vec![value]));
+            assert_eq!(label, orientation_label(value).unwrap());
+            assert_eq!(needs_rotation, (value != 1).to_string(), "orientation {value}");
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! `--within` / `--bbox`: keep only photos taken inside an area, judged by the
+//! decimal-degree position of the GPS columns ([`crate::Row::gps`]).
+
+use std::fmt;
+
+/// Mean Earth radius in kilometres, as the haversine formula usually takes it.
+const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+/// An area on the globe.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Geofence {
+    /// A circle: centre `(latitude, longitude)` and radius in kilometres.
+    Within { center: (f64, f64), radius_km: f64 },
+    /// A latitude / longitude rectangle, min and max of each; it doesn't wrap
+    /// around the 180° meridian.
+    Bbox { lat: (f64, f64), lon: (f64, f64) },
+}
+
+impl Geofence {
+    /// Parse `--within <lat>,<lon>,<radius>`, e.g. `48.85,2.35,5km`; the radius
+    /// takes `km` or `m`.
+    pub fn parse_within(text: &str) -> Result<Geofence, String> {
+        let invalid = |why: String| format!("invalid --within '{text}': {why} (expected <lat>,<lon>,<radius>, e.g. 48.85,2.35,5km)");
+        let parts: Vec<&str> = text.split(',').map(str::trim).collect();
+        let [lat, lon, radius] = parts[..] else {
+            return Err(invalid(format!("{} values instead of 3", parts.len())));
+        };
+        let center = (latitude(lat).map_err(invalid)?, longitude(lon).map_err(invalid)?);
+        let (number, scale) = if let Some(km) = radius.strip_suffix("km") {
+            (km, 1.0)
+        } else if let Some(m) = radius.strip_suffix('m') {
+            (m, 0.001)
+        } else {
+            return Err(invalid(format!("the radius '{radius}' needs a unit, km or m")));
+        };
+        let radius_km = number.trim().parse::<f64>().ok().filter(|r| r.is_finite() && *r > 0.0);
+        let radius_km = radius_km.ok_or_else(|| invalid(format!("'{radius}' is not a positive distance")))?;
+        Ok(Geofence::Within { center, radius_km: radius_km * scale })
+    }
+
+    /// Parse `--bbox <lat1>,<lon1>,<lat2>,<lon2>`: two opposite corners, in
+    /// either order.
+    pub fn parse_bbox(text: &str) -> Result<Geofence, String> {
+        let invalid = |why: String| format!("invalid --bbox '{text}': {why} (expected <lat1>,<lon1>,<lat2>,<lon2>, e.g. 48.81,2.22,48.90,2.47)");
+        let parts: Vec<&str> = text.split(',').map(str::trim).collect();
+        let [lat1, lon1, lat2, lon2] = parts[..] else {
+            return Err(invalid(format!("{} values instead of 4", parts.len())));
+        };
+        let (lat1, lat2) = (latitude(lat1).map_err(invalid)?, latitude(lat2).map_err(invalid)?);
+        let (lon1, lon2) = (longitude(lon1).map_err(invalid)?, longitude(lon2).map_err(invalid)?);
+        Ok(Geofence::Bbox {
+            lat: (lat1.min(lat2), lat1.max(lat2)),
+            lon: (lon1.min(lon2), lon1.max(lon2)),
+        })
+    }
+
+    /// Whether `(latitude, longitude)` lies inside, edges included.
+    pub fn contains(&self, (lat, lon): (f64, f64)) -> bool {
+        match *self {
+            Geofence::Within { center, radius_km } => haversine_km(center, (lat, lon)) <= radius_km,
+            Geofence::Bbox { lat: (south, north), lon: (west, east) } => {
+                (south..=north).contains(&lat) && (west..=east).contains(&lon)
+            }
+        }
+    }
+}
+
+/// The text `--within` / `--bbox` take, radius in km; parses back to the same area.
+impl fmt::Display for Geofence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Geofence::Within { center: (lat, lon), radius_km } => write!(f, "{lat},{lon},{radius_km}km"),
+            Geofence::Bbox { lat: (south, north), lon: (west, east) } => write!(f, "{south},{west},{north},{east}"),
+        }
+    }
+}
+
+/// Great-circle distance between two positions in kilometres.
+pub fn haversine_km((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let half_dphi = (phi2 - phi1) / 2.0;
+    let half_dlambda = (lon2 - lon1).to_radians() / 2.0;
+    let a = half_dphi.sin().powi(2) + phi1.cos() * phi2.cos() * half_dlambda.sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+}
+
+fn latitude(text: &str) -> Result<f64, String> {
+    degrees(text, "latitude", 90.0)
+}
+
+fn longitude(text: &str) -> Result<f64, String> {
+    degrees(text, "longitude", 180.0)
+}
+
+/// A decimal-degree coordinate between `-limit` and `limit`.
+fn degrees(text: &str, what: &str, limit: f64) -> Result<f64, String> {
+    let value: f64 = text.parse().map_err(|_| format!("{what} '{text}' is not a number"))?;
+    if !(-limit..=limit).contains(&value) {
+        return Err(format!("{what} {text} is out of range (-{limit} to {limit})"));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{haversine_km, Geofence};
+
+    const PARIS: (f64, f64) = (48.8566, 2.3522);
+    const LONDON: (f64, f64) = (51.5074, -0.1278);
+
+    #[test]
+    fn haversine_matches_known_distances() {
+        let d = haversine_km(PARIS, LONDON);
+        assert!((d - 343.5).abs() < 1.0, "Paris to London: {d} km");
+        assert_eq!(haversine_km(PARIS, PARIS), 0.0);
+        // half the equator
+        let d = haversine_km((0.0, 0.0), (0.0, 180.0));
+        assert!((d - 20_015.1).abs() < 1.0, "{d} km");
+    }
+
+    #[test]
+    fn within_takes_km_and_m() {
+        let km = Geofence::parse_within("48.8566,2.3522,5km").unwrap();
+        assert_eq!(km, Geofence::Within { center: PARIS, radius_km: 5.0 });
+        let m = Geofence::parse_within(" 48.8566 , 2.3522 , 500 m ").unwrap();
+        assert_eq!(m, Geofence::Within { center: PARIS, radius_km: 0.5 });
+        assert!(Geofence::parse_within("48.8566,2.3522,400km").unwrap().contains(LONDON));
+        assert!(!Geofence::parse_within("48.8566,2.3522,300km").unwrap().contains(LONDON));
+    }
+
+    #[test]
+    fn bbox_corners_go_in_either_order() {
+        let a = Geofence::parse_bbox("48.81,2.22,48.90,2.47").unwrap();
+        let b = Geofence::parse_bbox("48.90,2.47,48.81,2.22").unwrap();
+        assert_eq!(a, b);
+        assert!(a.contains(PARIS));
+        assert!(a.contains((48.81, 2.22)), "edges are inside");
+        assert!(!a.contains(LONDON));
+    }
+
+    #[test]
+    fn areas_print_as_they_parse() {
+        for text in ["48.8566,2.3522,0.5km", "-33.8688,151.2093,12.5km"] {
+            assert_eq!(Geofence::parse_within(text).unwrap().to_string(), text);
+        }
+        let bbox = Geofence::parse_bbox("48.9,2.47,48.81,2.22").unwrap();
+        assert_eq!(bbox.to_string(), "48.81,2.22,48.9,2.47");
+    }
+
+    #[test]
+    fn malformed_areas_say_what_is_wrong() {
+        let cases = [
+            (Geofence::parse_within("91,2.35,5km"), "latitude 91 is out of range (-90 to 90)"),
+            (Geofence::parse_within("48.85,181,5km"), "longitude 181 is out of range (-180 to 180)"),
+            (Geofence::parse_within("48.85,2.35"), "2 values instead of 3"),
+            (Geofence::parse_within("48.85,2.35,fivekm"), "'fivekm' is not a positive distance"),
+            (Geofence::parse_within("48.85,2.35,-5km"), "'-5km' is not a positive distance"),
+            (Geofence::parse_within("48.85,2.35,5"), "the radius '5' needs a unit, km or m"),
+            (Geofence::parse_within("north,2.35,5km"), "latitude 'north' is not a number"),
+            (Geofence::parse_bbox("48.81,2.22,48.90"), "3 values instead of 4"),
+            (Geofence::parse_bbox("48.81,2.22,95,2.47"), "latitude 95 is out of range (-90 to 90)"),
+            (Geofence::parse_bbox("48.81,-200,48.90,2.47"), "longitude -200 is out of range (-180 to 180)"),
+        ];
+        for (result, why) in cases {
+            let message = result.unwrap_err();
+            assert!(message.contains(why), "{message:?} should say {why:?}");
+        }
+        let message = Geofence::parse_within("1,2").unwrap_err();
+        assert!(message.starts_with("invalid --within '1,2': "), "{message}");
+        assert!(message.ends_with("(expected <lat>,<lon>,<radius>, e.g. 48.85,2.35,5km)"), "{message}");
+        let message = Geofence::parse_bbox("").unwrap_err();
+        assert!(message.starts_with("invalid --bbox '': "), "{message}");
+    }
+}
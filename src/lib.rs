@@ -0,0 +1,384 @@
+//! Library side of `exif_etl`: find images, pull their EXIF into [`Row`]s.
+//!
+//! The binary wires these together for the drop-in-and-run use case; they are
+//! public so other tools can feed in files or bytes from anywhere.
+
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use rexif::{parse_buffer, ExifError, IfdKind};
+
+#[cfg(feature = "tui")]
+pub mod browse;
+pub mod checkpoint;
+pub mod config;
+pub mod extractor;
+pub mod fields;
+pub mod geofence;
+pub mod organize;
+pub mod output;
+#[cfg(feature = "parquet")]
+mod parquet_output;
+pub mod preview;
+#[cfg(feature = "raw")]
+pub mod raw;
+pub mod report;
+mod row;
+pub mod sniff;
+pub mod tags;
+#[cfg(test)]
+mod testutil;
+pub mod timing;
+pub mod verify;
+pub mod walk;
+
+pub use extractor::ColumnExtractor;
+pub use row::{header, Column, Row, Sanitize, ALL_COLUMNS, BASE_COLUMNS, LONG_HEADER, LONG_RAW_HEADER, SCHEMA_VERSION, TAGS_HEADER};
+/// The EXIF parser, re-exported for [`ColumnExtractor`] implementations.
+pub use rexif;
+
+/// Return all files in `dir_path` whose extension is one of `extensions` (lower
+/// case, without the dot; matched case‑insensitively), sorted. The folder is read
+/// once however many extensions there are, and a file reached under two names
+/// (a link next to its target) is listed once, see [`dedupe_by_target`].
+/// Fails when the folder can't be opened, so that isn't mistaken for an empty one.
+pub fn find_files_by_extension(dir_path: &str, extensions: &[&str]) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = find_all_files(dir_path)?
+        .into_iter()
+        .filter(|path| has_extension(path, extensions))
+        .collect();
+    dedupe_by_target(&mut files);
+    files.sort(); // deterministic ordering
+    Ok(files)
+}
+
+/// Whether `path`'s extension is one of `extensions` (lower case, without the dot), in any case.
+pub fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+/// Return every regular file directly inside `dir_path`, whatever its name (for `--sniff`).
+/// Fails when the folder itself can't be listed (missing, no permission, not a
+/// folder); an entry that can't be read while listing is skipped.
+pub fn find_all_files(dir_path: &str) -> io::Result<Vec<PathBuf>> {
+    Ok(fs::read_dir(dir_path)?
+        .filter_map(|entry_res| {
+            let entry = entry_res.ok()?;
+            // DirEntry::file_type is free on most platforms, unlike a stat per path
+            let file_type = entry.file_type().ok()?;
+            let is_file = file_type.is_file() || (file_type.is_symlink() && entry.path().is_file());
+            is_file.then(|| entry.path())
+        })
+        .collect())
+}
+
+/// Paths from a list such as `find` prints: one per `separator` byte (`\n`, or
+/// `\0` for `find -print0`). Empty entries are dropped, and so is the `\r` of
+/// Windows line endings in a newline-separated list.
+pub fn read_file_list(mut reader: impl Read, separator: u8) -> io::Result<Vec<PathBuf>> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    Ok(data
+        .split(|&b| b == separator)
+        .map(|entry| if separator == b'\n' { entry.strip_suffix(b"\r").unwrap_or(entry) } else { entry })
+        .filter(|entry| !entry.is_empty())
+        .map(path_from_bytes)
+        .collect())
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Whether `path` itself is a symbolic link (whatever it points at).
+pub fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink())
+}
+
+/// Drop files that turn out to be the same file once links are resolved (a
+/// symlink next to its target, two links to one image), so no image is read
+/// twice. Real files are kept over links, then the first path in sort order.
+/// Returns how many were dropped; `files` ends up sorted by that preference.
+pub fn dedupe_by_target(files: &mut Vec<PathBuf>) -> usize {
+    files.sort_by_cached_key(|f| (is_symlink(f), f.clone()));
+    let before = files.len();
+    let mut seen = HashSet::new();
+    // a path that can't be resolved is kept; reading it will report the problem
+    files.retain(|f| fs::canonicalize(f).map_or(true, |target| seen.insert(target)));
+    before - files.len()
+}
+
+/// Keep one row per file, for the binary's `--dedup-output`: paths that resolve to the same
+/// file (`a.jpg` and `./a.jpg` in a `--files-from` list) count as one, and the
+/// last of them in output order is kept. Returns how many rows were dropped.
+pub fn dedup_rows(rows: &mut Vec<&Row>) -> usize {
+    let keys: Vec<PathBuf> = rows
+        .iter()
+        .map(|row| fs::canonicalize(&row.path).unwrap_or_else(|_| row.path.clone()))
+        .collect();
+    let last: HashMap<&PathBuf, usize> = keys.iter().enumerate().map(|(i, key)| (key, i)).collect();
+    let before = rows.len();
+    let mut i = 0;
+    rows.retain(|_| {
+        let keep = last[&keys[i]] == i;
+        i += 1;
+        keep
+    });
+    before - rows.len()
+}
+
+/// Broad class of an extraction failure, as recorded in the error log and in
+/// the `error` column. The names are the error log's from before the split
+/// into IO and parse errors (`io`, not `io_error`), which scripts and
+/// `--json-errors` kinds already match on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCategory {
+    Io,                // the file could not be opened or read
+    ExifParse,         // recognised image, but its EXIF is missing or broken
+    UnsupportedFormat, // not a JPEG/TIFF as far as rexif can tell
+}
+
+impl ErrorCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCategory::Io => "io",
+            ErrorCategory::ExifParse => "exif-parse",
+            ErrorCategory::UnsupportedFormat => "unsupported-format",
+        }
+    }
+}
+
+/// Why one file produced no row.
+#[derive(Debug)]
+pub struct ExtractError {
+    pub path: PathBuf,
+    pub category: ErrorCategory,
+    pub message: String,
+    pub file_size: Option<u64>, // None when even the size could not be read
+}
+
+impl ExtractError {
+    fn io(path: PathBuf, err: std::io::Error) -> ExtractError {
+        let file_size = fs::metadata(&path).ok().map(|m| m.len());
+        ExtractError {
+            path,
+            category: ErrorCategory::Io,
+            message: err.to_string(),
+            file_size,
+        }
+    }
+
+    fn exif(path: PathBuf, err: ExifError, file_size: u64) -> ExtractError {
+        let category = match err {
+            ExifError::IoError(_) => ErrorCategory::Io,
+            ExifError::FileTypeUnknown => ErrorCategory::UnsupportedFormat,
+            _ => ErrorCategory::ExifParse,
+        };
+        ExtractError {
+            path,
+            category,
+            message: err.to_string(),
+            file_size: Some(file_size),
+        }
+    }
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.path.display(), self.category.as_str(), self.message)
+    }
+}
+
+impl Error for ExtractError {}
+
+/// Knobs for [`extract_exif_with`]; the default matches [`extract_exif`].
+#[derive(Clone, Debug, Default)]
+pub struct ExtractOptions {
+    /// Extra attempts after an IO error (flaky network mounts); parse errors are never retried.
+    pub retries: u32,
+    /// Which copy of a repeated tag to keep: the one in this IFD, else the first.
+    pub prefer_ifd: Option<IfdKind>,
+    /// Custom columns, filled in after the built-in fields in this order.
+    pub extractors: Vec<Arc<dyn ColumnExtractor>>,
+}
+
+impl ExtractOptions {
+    /// Names of the custom columns, in output order.
+    pub fn custom_columns(&self) -> Vec<String> {
+        self.extractors.iter().map(|x| x.name().to_string()).collect()
+    }
+}
+
+/// Lazily extract every JPEG (and, with the `raw` feature, RAW file) under
+/// `dir`, subfolders included: each file is read and parsed only when the next
+/// row is asked for, so a huge tree never sits in memory at once. Files come
+/// in listing order; a folder that can't be listed yields an IO error for it.
+pub fn extract_dir<'a>(dir: &Path, opts: &'a ExtractOptions) -> impl Iterator<Item = Result<Row, ExtractError>> + 'a {
+    #[cfg(feature = "raw")]
+    let raw = sniff::RAW_EXTENSIONS;
+    #[cfg(not(feature = "raw"))]
+    let raw: &[&str] = &[];
+    walk::lazy_walk(dir).filter_map(move |found| match found {
+        Ok(path) if has_extension(&path, &["jpg", "jpeg"]) || has_extension(&path, raw) => {
+            Some(extract_exif_with(&path, opts))
+        }
+        Ok(_) => None,
+        Err((dir, e)) => Some(Err(ExtractError::io(dir, e))),
+    })
+}
+
+/// Pause before retry number `attempt` (1-based): 100 ms, 200 ms, 300 ms, …
+fn retry_backoff(attempt: u32) -> Duration {
+    Duration::from_millis(100 * u64::from(attempt))
+}
+
+/// Extract EXIF data for one file.
+/// Reading the file and parsing its bytes are separate steps so IO trouble is
+/// reported as such rather than as a parse failure.
+pub fn extract_exif(file_path: &Path) -> Result<Row, ExtractError> {
+    extract_exif_with(file_path, &ExtractOptions::default())
+}
+
+/// [`extract_exif`] with explicit options.
+pub fn extract_exif_with(file_path: &Path, opts: &ExtractOptions) -> Result<Row, ExtractError> {
+    let mut attempt = 0;
+    let data = loop {
+        match fs::read(file_path) {
+            Ok(data) => break data,
+            Err(_) if attempt < opts.retries => {
+                attempt += 1;
+                thread::sleep(retry_backoff(attempt));
+            }
+            Err(e) => {
+                let mut error = ExtractError::io(file_path.to_path_buf(), e);
+                if attempt > 0 {
+                    error.message = format!("{} (after {} retries)", error.message, attempt);
+                }
+                return Err(error);
+            }
+        }
+    };
+    extract_exif_from_bytes_with(&file_path.to_string_lossy(), &data, opts)
+}
+
+/// Extract EXIF data from an image already in memory (a zip member, a download, …).
+/// `name` stands in for the path in the resulting row and in errors.
+pub fn extract_exif_from_bytes(name: &str, data: &[u8]) -> Result<Row, ExtractError> {
+    extract_exif_from_bytes_with(name, data, &ExtractOptions::default())
+}
+
+/// [`extract_exif_from_bytes`] with explicit options (only the extractors apply here).
+pub fn extract_exif_from_bytes_with(name: &str, data: &[u8], opts: &ExtractOptions) -> Result<Row, ExtractError> {
+    let path = PathBuf::from(name);
+    // RAW containers are unwrapped first; type sniffing still sees the real file
+    #[cfg(feature = "raw")]
+    let unwrapped = raw::exif_container(data);
+    #[cfg(feature = "raw")]
+    let parsed = &*unwrapped;
+    #[cfg(not(feature = "raw"))]
+    let parsed = data;
+    let exif = parse_buffer(parsed).map_err(|e| ExtractError::exif(path.clone(), e, data.len() as u64))?;
+    let custom = opts
+        .extractors
+        .iter()
+        .map(|x| (x.name().to_string(), x.value(&exif, &path)))
+        .collect();
+    let entries = tags::dedupe_entries(&exif.entries, opts.prefer_ifd);
+    let detected_type = sniff::sniff_bytes(data);
+    let mut row = Row::from_exif(path, exif.mime, &entries, detected_type);
+    if row.size.is_none() && detected_type == Some(sniff::ImageType::Jpeg) {
+        row.size = sniff::jpeg_dimensions(data);
+    }
+    row.file_size = Some(data.len() as u64);
+    row.custom = custom;
+    Ok(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dedup_rows, find_files_by_extension};
+    use crate::{testutil::ScratchDir, Row};
+
+    #[test]
+    fn extensions_match_in_any_case_and_are_sorted() {
+        let dir = ScratchDir::new("find-case");
+        let upper = dir.file("A.JPG", b"");
+        let mixed = dir.file("b.Jpeg", b"");
+        dir.file("c.png", b"");
+        dir.file("jpg", b""); // a name without an extension
+        let found = find_files_by_extension(&dir.path().to_string_lossy(), &["jpg", "jpeg"]).unwrap();
+        assert_eq!(found, [upper, mixed]);
+    }
+
+    #[test]
+    fn a_file_matched_by_several_patterns_is_listed_once() {
+        let dir = ScratchDir::new("find-patterns");
+        let file = dir.file("a.jpg", b"");
+        let found = find_files_by_extension(&dir.path().to_string_lossy(), &["jpg", "JPG", "jpeg"]).unwrap();
+        assert_eq!(found, [file]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_link_next_to_its_target_is_listed_once() {
+        let dir = ScratchDir::new("find-link");
+        let target = dir.file("a.jpg", b"");
+        std::os::unix::fs::symlink(&target, dir.path().join("link.JPG")).unwrap();
+        let found = find_files_by_extension(&dir.path().to_string_lossy(), &["jpg", "jpeg"]).unwrap();
+        assert_eq!(found, [target]);
+    }
+
+    #[test]
+    fn an_empty_folder_is_not_an_error() {
+        let dir = ScratchDir::new("find-empty");
+        assert!(find_files_by_extension(&dir.path().to_string_lossy(), &["jpg"]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_missing_folder_is_an_error() {
+        let dir = ScratchDir::new("find-missing");
+        let missing = dir.path().join("not-there");
+        let err = find_files_by_extension(&missing.to_string_lossy(), &["jpg"]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn rows_for_one_file_keep_only_the_last() {
+        let dir = ScratchDir::new("dedup-rows");
+        let a = dir.file("a.jpg", b"");
+        let b = dir.file("b.jpg", b"");
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        let rows = [
+            Row::skipped(a.clone(), "first"),
+            Row::skipped(b, "other file"),
+            Row::skipped(dir.path().join("sub").join("..").join("a.jpg"), "second"),
+        ];
+        let mut shown: Vec<&Row> = rows.iter().collect();
+        assert_eq!(dedup_rows(&mut shown), 1);
+        let kept: Vec<_> = shown.iter().map(|row| row.error.as_deref().unwrap()).collect();
+        assert_eq!(kept, ["other file", "second"]);
+    }
+
+    #[test]
+    fn rows_for_missing_files_are_compared_as_written() {
+        let rows = [Row::skipped("gone.jpg".into(), "first"), Row::skipped("./gone.jpg".into(), "second")];
+        let mut shown: Vec<&Row> = rows.iter().collect();
+        assert_eq!(dedup_rows(&mut shown), 0);
+    }
+}
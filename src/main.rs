@@ -1,89 +1,2077 @@
-use std::{error::Error, fs, path::PathBuf, thread, time::Duration};
+use std::{
+    collections::{btree_map::Entry, BTreeMap, BTreeSet, HashMap},
+    env,
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
-use chrono::Local;
+use chrono::{DateTime, FixedOffset, Local, Utc};
 use csv::WriterBuilder;
+use exif_etl::{
+    checkpoint::{file_list_hash, Checkpoint},
+    geofence::Geofence,
+    report::Report,
+    verify::{self, Drift},
+    config::{parse_assume_offset, parse_duration, parse_ifd, parse_layout, parse_size, parse_time_format, GroupBy, Order, OrderBy, ScanOptions, SortBy, Zone, DEFAULT_CONFIG_FILE},
+    dedup_rows, dedupe_by_target, extract_exif_with, fields, find_all_files, has_extension, is_symlink, organize, read_file_list,
+    output::{create_writer, sanitize_file_component, Destination, Field, Format, Layout, PathStyle, RowWriter},
+    preview::preview_table,
+    sniff::{self, RAW_EXTENSIONS},
+    timing::Timings,
+    walk::{lazy_walk, stream_files, walk_files},
+    ErrorCategory, ExtractError, Row,
+};
+#[cfg(feature = "tui")]
+use exif_etl::browse::browse;
 use rayon::prelude::*;
-use rexif::parse_file;
-
-/// Return all files in `dir_path` whose extension matches `extension` (case‑insensitive).
-fn find_files_by_extension(dir_path: &str, extension: &str) -> Vec<PathBuf> {
-    fs::read_dir(dir_path)
-        .into_iter()             // Option → iterator (empty if read_dir fails)
-        .flatten()               // ReadDir → DirEntry values
-        .filter_map(|entry_res| {
-            let entry = entry_res.ok()?;
-            let path = entry.path();
-            if path.is_file() {
-                if let Some(ext) = path.extension() {
-                    if ext.eq_ignore_ascii_case(extension) {
-                        return Some(path);
-                    }
+use terminal_size::{terminal_size_of, Width};
+
+/// Set by `--stdout`: the data owns standard output, so messages move to stderr.
+static DATA_ON_STDOUT: AtomicBool = AtomicBool::new(false);
+
+/// Set once output files are open: from then on Ctrl-C only asks the writers to
+/// stop, so a gzip stream still gets its trailer instead of being cut off.
+static WRITING: AtomicBool = AtomicBool::new(false);
+
+/// Set by Ctrl-C while [`WRITING`]; the writers stop after the current row.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--json-errors`: [`report_error`] writes JSON instead of prose.
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Report a failure on stderr: `prose` as it is, or under `--json-errors` one
+/// line `{"error": message, "kind": kind, "path": path}` for wrapper scripts.
+/// `kind` is a file's error category (`io`, `exif-parse`, `unsupported-format`)
+/// or what failed around the run (`usage`, `input`, `output`, …).
+fn report_error(prose: &str, kind: &str, path: Option<&Path>, message: &str) {
+    if JSON_ERRORS.load(Ordering::Relaxed) {
+        let path = path.map(|p| p.to_string_lossy());
+        eprintln!("{}", serde_json::json!({ "error": message, "kind": kind, "path": path }));
+    } else {
+        eprintln!("{}", prose);
+    }
+}
+
+/// `println!` for progress and summary messages; goes to stderr under `--stdout`.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if DATA_ON_STDOUT.load(Ordering::Relaxed) {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// Error-column marker for files over `--max-size`.
+const SKIPPED_TOO_LARGE: &str = "skipped: too large";
+/// `error` cell of a `--files-from` entry that isn't there, or isn't a file.
+const SKIPPED_MISSING: &str = "skipped: no such file";
+const SKIPPED_NOT_A_FILE: &str = "skipped: not a regular file";
+/// `error` cell of a file `--sniff` picked in a format rexif can't read (PNG, HEIC).
+const SKIPPED_NO_READER: &str = "skipped: no EXIF reader for this type";
+
+/// Settings from the config file: `--config <file>` if given, else
+/// `exif_tool.toml` in the working directory if there is one, else the defaults.
+fn load_config() -> Result<ScanOptions, String> {
+    let mut args = env::args().skip(1);
+    let explicit = loop {
+        match args.next().as_deref() {
+            Some("--config") => break Some(PathBuf::from(args.next().ok_or("--config needs a file name")?)),
+            Some(_) => continue,
+            None => break None,
+        }
+    };
+    let path = match explicit {
+        Some(path) => path,
+        None if Path::new(DEFAULT_CONFIG_FILE).is_file() => PathBuf::from(DEFAULT_CONFIG_FILE),
+        None => return Ok(ScanOptions::default()),
+    };
+    let (opts, unknown) = ScanOptions::from_file(&path).map_err(|e| format!("Error reading config: {e}"))?;
+    for key in unknown {
+        eprintln!("Warning: unknown key '{}' in {} ignored", key, path.display());
+    }
+    Ok(opts)
+}
+
+/// Which settings dump `--print-config` / `--print-schema` asked for instead of a run.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Print {
+    Nothing,
+    Config,
+    Schema,
+}
+
+/// Apply `std::env::args` on top of the config file's settings. The second value
+/// says whether to print something instead of running.
+fn parse_args() -> Result<(ScanOptions, Print), String> {
+    let mut opts = load_config()?;
+    let mut print = Print::Nothing;
+    let mut bom_flag = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => {
+                args.next(); // already read by load_config
+            }
+            "--print-config" => print = Print::Config,
+            "--print-schema" => print = Print::Schema,
+            "--organize" | "--organize-into" => {
+                let dest = args.next().ok_or_else(|| format!("{arg} needs a destination folder"))?;
+                opts.organize = Some(PathBuf::from(dest));
+            }
+            "--by" => {
+                let by = args.next().ok_or("--by needs a folder layout, e.g. year/month")?;
+                opts.layout = Some(parse_layout(&by)?);
+            }
+            "--require-exif-date" => opts.require_exif_date = true,
+            "--dry-run" => opts.dry_run = true,
+            "--touch" => opts.touch = true,
+            "--move" => opts.move_files = true,
+            "--error-log" => {
+                let file = args.next().ok_or("--error-log needs a file name")?;
+                opts.error_log = Some(PathBuf::from(file));
+            }
+            "--max-size" => {
+                let size = args.next().ok_or("--max-size needs a size, e.g. 200M")?;
+                opts.max_size = parse_size(&size)?;
+            }
+            "--limit" => {
+                let n = args.next().ok_or("--limit needs a number of files")?;
+                match n.parse::<usize>() {
+                    Ok(n) if n > 0 => opts.limit = Some(n),
+                    _ => return Err(format!("--limit must be a positive number of files, not '{n}'")),
                 }
             }
-            None
+            "--sample" => {
+                let n = args.next().ok_or("--sample needs a number of files")?;
+                match n.parse::<usize>() {
+                    Ok(n) if n > 0 => opts.sample = Some(n),
+                    _ => return Err(format!("--sample must be a positive number of files, not '{n}'")),
+                }
+            }
+            "--seed" => {
+                let seed = args.next().ok_or("--seed needs a number")?;
+                opts.seed = Some(seed.parse().map_err(|_| format!("invalid seed '{seed}'"))?);
+            }
+            "--threads" => {
+                let n = args.next().ok_or("--threads needs a number")?;
+                opts.threads = n.parse().map_err(|_| format!("invalid thread count '{n}'"))?;
+            }
+            "--max-open-files" => {
+                let n = args.next().ok_or("--max-open-files needs a number")?;
+                opts.max_open = Some(n.parse().map_err(|_| format!("invalid open file cap '{n}'"))?);
+            }
+            "--pretty-exposure" => opts.pretty_exposure = true,
+            "--raw-tags" => opts.raw_tags = true,
+            "--kml" => {
+                let file = args.next().ok_or("--kml needs a file name")?;
+                opts.kml = Some(PathBuf::from(file));
+            }
+            "--manifest" => {
+                let file = args.next().ok_or("--manifest needs a file name")?;
+                opts.manifest = Some(PathBuf::from(file));
+            }
+            "--rollup" => {
+                let file = args.next().ok_or("--rollup needs a file name")?;
+                opts.rollup = Some(PathBuf::from(file));
+            }
+            "--rollup-depth" => {
+                let n = args.next().ok_or("--rollup-depth needs a number of folders")?;
+                opts.rollup_depth = Some(n.parse().map_err(|_| format!("invalid folder count '{n}'"))?);
+            }
+            "--raw-values" => opts.raw_values = true,
+            "--fail-fast" => opts.fail_fast = true,
+            "--count-only" => opts.count_only = true,
+            "--recursive" => opts.recursive = true,
+            "--max-depth" => {
+                let n = args.next().ok_or("--max-depth needs a number of folder levels")?;
+                opts.max_depth = Some(n.parse().map_err(|_| format!("invalid folder depth '{n}'"))?);
+            }
+            "--files-from" => {
+                let list = args.next().ok_or("--files-from needs a file (or - for stdin)")?;
+                opts.files_from = Some(PathBuf::from(list));
+            }
+            "-0" => opts.null_separated = true,
+            "--follow-symlinks" => opts.follow_symlinks = true,
+            "--sniff" => opts.sniff = true,
+            "--raw" => opts.raw = true,
+            "--long" => opts.long = true,
+            "--absolute-paths" => opts.absolute_paths = true,
+            "--retries" => {
+                let n = args.next().ok_or("--retries needs a count")?;
+                opts.retries = n.parse().map_err(|_| format!("invalid retry count '{n}'"))?;
+            }
+            "--prefer-ifd" => {
+                let name = args.next().ok_or("--prefer-ifd needs an IFD name")?;
+                opts.prefer_ifd = Some(parse_ifd(&name)?);
+            }
+            "--tz" => match args.next().as_deref() {
+                Some("utc") => opts.tz = Zone::Utc,
+                Some("local") => opts.tz = Zone::Local,
+                _ => return Err("--tz must be 'utc' or 'local'".into()),
+            },
+            "--time-format" => {
+                let format = args.next().ok_or("--time-format needs a strftime pattern")?;
+                opts.time_format = Some(parse_time_format(&format)?);
+            }
+            "--format" => {
+                opts.format = match args.next().as_deref() {
+                    Some("csv") => Format::Csv,
+                    Some("tsv") => Format::Tsv,
+                    Some("jsonl") => Format::Jsonl,
+                    Some("kml") => Format::Kml,
+                    Some("html") => Format::Html,
+                    Some("md") => Format::Md,
+                    Some("xml") => Format::Xml,
+                    Some("parquet") => Format::Parquet,
+                    _ => return Err("--format must be one of: csv, tsv, jsonl, kml, html, md, xml, parquet".into()),
+                }
+            }
+            "--columns" => {
+                let list = args.next().ok_or("--columns needs a comma-separated list of column names")?;
+                opts.columns = list
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|c| !c.is_empty())
+                    .map(String::from)
+                    .collect();
+            }
+            "--other-tags" => opts.other_tags = true,
+            "--output" => {
+                let file = args.next().ok_or("--output needs a file name")?;
+                opts.output = Some(PathBuf::from(file));
+            }
+            "--compress" => opts.compress = true,
+            "--chunk-rows" => {
+                let n = args.next().ok_or("--chunk-rows needs a number of rows")?;
+                match n.parse::<usize>() {
+                    Ok(n) if n > 0 => opts.chunk_rows = Some(n),
+                    _ => return Err(format!("--chunk-rows must be a positive number of rows, not '{n}'")),
+                }
+            }
+            "--stdout" => opts.stdout = true,
+            "--json-errors" => opts.json_errors = true,
+            "--on-complete" => opts.on_complete = Some(args.next().ok_or("--on-complete needs a command")?),
+            "--bom" => {
+                opts.bom = true;
+                bom_flag = true;
+            }
+            "--tui" => opts.tui = true,
+            "--preview" => {
+                let n = args.next().ok_or("--preview needs a number of rows")?;
+                opts.preview = n.parse().map_err(|_| format!("invalid preview row count '{n}'"))?;
+            }
+            "--max-cell-width" => {
+                let n = args.next().ok_or("--max-cell-width needs a number of characters")?;
+                opts.max_cell_width = n.parse().map_err(|_| format!("invalid cell width '{n}'"))?;
+            }
+            "--hash-paths" => opts.hash_paths = true,
+            "--hash-only" => opts.hash_only = true,
+            "--path-map" => {
+                let file = args.next().ok_or("--path-map needs a file name")?;
+                opts.path_map = Some(PathBuf::from(file));
+            }
+            "--exclude-ext" => {
+                let list = args.next().ok_or("--exclude-ext needs a comma-separated list of extensions")?;
+                opts.exclude_ext = list
+                    .split(',')
+                    .map(|e| e.trim().trim_start_matches('.').to_ascii_lowercase())
+                    .filter(|e| !e.is_empty())
+                    .collect();
+            }
+            "--within" => {
+                let area = args.next().ok_or("--within needs <lat>,<lon>,<radius>, e.g. 48.85,2.35,5km")?;
+                opts.within = Some(Geofence::parse_within(&area)?);
+            }
+            "--bbox" => {
+                let area = args.next().ok_or("--bbox needs <lat1>,<lon1>,<lat2>,<lon2>")?;
+                opts.bbox = Some(Geofence::parse_bbox(&area)?);
+            }
+            "--include-no-gps" => opts.include_no_gps = true,
+            "--require-tags" => {
+                let list = args.next().ok_or("--require-tags needs a comma-separated list of tag names")?;
+                opts.require_tags = list
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .map(String::from)
+                    .collect();
+            }
+            "--only-missing" => opts.only_missing = true,
+            "--min-tags" => {
+                let n = args.next().ok_or("--min-tags needs a number of tags")?;
+                opts.min_tags = n.parse().map_err(|_| format!("invalid tag count '{n}'"))?;
+            }
+            "--date-drift" => {
+                let threshold = args.next().ok_or("--date-drift needs a threshold, e.g. 1h")?;
+                opts.date_drift = Some(parse_duration(&threshold)?);
+            }
+            "--checkpoint" => {
+                let file = args.next().ok_or("--checkpoint needs a file name")?;
+                opts.checkpoint = Some(PathBuf::from(file));
+            }
+            "--resume" => {
+                let file = args.next().ok_or("--resume needs the checkpoint file")?;
+                opts.resume = Some(PathBuf::from(file));
+            }
+            "--checkpoint-every" => {
+                let n = args.next().ok_or("--checkpoint-every needs a number of files")?;
+                opts.every = Some(n.parse().map_err(|_| format!("invalid file count '{n}'"))?);
+            }
+            "--checkpoint-interval" => {
+                let interval = args.next().ok_or("--checkpoint-interval needs a duration, e.g. 5m")?;
+                opts.interval = Some(parse_duration(&interval)?);
+            }
+            "--timing" => opts.timing = true,
+            "--timing-json" => {
+                let file = args.next().ok_or("--timing-json needs a file name")?;
+                opts.timing_json = Some(PathBuf::from(file));
+            }
+            "--order-by" => match args.next().as_deref() {
+                Some("path") => opts.order_by = OrderBy::Path,
+                Some("mtime") => opts.order_by = OrderBy::Mtime,
+                Some("size") => opts.order_by = OrderBy::Size,
+                _ => return Err("--order-by must be one of: path, mtime, size".into()),
+            },
+            "--order" => match args.next().as_deref() {
+                Some("asc") => opts.order = Order::Asc,
+                Some("desc") => opts.order = Order::Desc,
+                _ => return Err("--order must be 'asc' or 'desc'".into()),
+            },
+            "--assume-offset" => {
+                let offset = args.next().ok_or("--assume-offset needs an offset, e.g. +02:00")?;
+                opts.offset = Some(parse_assume_offset(&offset)?);
+            }
+            "--dedup-output" => opts.dedup_output = true,
+            "--sort-by" => match args.next().as_deref() {
+                Some("path") => opts.sort_by = SortBy::Path,
+                Some("date") => opts.sort_by = SortBy::Date,
+                Some("model") => opts.sort_by = SortBy::Model,
+                Some("size") => opts.sort_by = SortBy::Size,
+                _ => return Err("--sort-by must be one of: path, date, model, size".into()),
+            },
+            "--group-by" => match args.next().as_deref() {
+                Some("camera") => opts.group_by = Some(GroupBy::Camera),
+                _ => return Err("--group-by supports: camera".into()),
+            },
+            other => return Err(format!("unknown argument: {other}")),
+        }
+    }
+    // `bom = true` in a config file is meant for files opened in Excel; a BOM
+    // would only confuse the next program in a pipe, so that takes the flag itself
+    if opts.stdout && !bom_flag {
+        opts.bom = false;
+    }
+    opts.validate()?;
+    Ok((opts, print))
+}
+
+/// The run's `csv_created_at` stamp: RFC 3339 local time unless `--tz` / `--time-format` say otherwise.
+fn created_at(opts: &ScanOptions) -> String {
+    match (&opts.time_format, opts.tz) {
+        (Some(format), Zone::Utc) => Utc::now().format(format).to_string(),
+        (Some(format), Zone::Local) => Local::now().format(format).to_string(),
+        (None, Zone::Utc) => Utc::now().to_rfc3339(),
+        (None, Zone::Local) => Local::now().to_rfc3339(),
+    }
+}
+
+/// Name of the single output file, and the stem of the per-group ones.
+const OUTPUT_STEM: &str = "exif_output";
+
+/// How every output file of this run is laid out.
+fn output_layout(opts: &ScanOptions) -> Layout {
+    let custom_columns = opts.extract_options().custom_columns();
+    Layout {
+        format: opts.format,
+        columns: opts.fixed_columns(),
+        long: opts.long,
+        created_at: created_at(opts),
+        scan_dir: match env::current_dir() {
+            Ok(dir) if !opts.hash_paths => dir.display().to_string(),
+            _ => ".".to_string(), // hashed paths hide the folder layout, so the report does too
+        },
+        max_cell_width: opts.max_cell_width,
+        path_style: opts.path_style(),
+        bom: opts.bom,
+        compress: opts.compressed(),
+        fields: opts.fields(&custom_columns),
+        other_tags: opts.other_tags,
+        raw_tags: opts.raw_tags,
+        custom_columns,
+    }
+}
+
+/// Write the `--kml` side file; returns how many photos had GPS and made it in.
+fn write_kml(path: &Path, rows: &[&Row], opts: &ScanOptions) -> Result<usize, Box<dyn Error>> {
+    let layout = Layout {
+        format: Format::Kml,
+        compress: false,
+        ..output_layout(opts)
+    };
+    let mut out = create_writer(&Destination::File(path.to_path_buf()), &layout)?;
+    for row in rows {
+        out.write_row(row)?;
+    }
+    let (_, placemarks) = out.finish()?;
+    Ok(placemarks)
+}
+
+/// Write all rows to `exif_output.<ext>` (`.csv` unless `--format` says otherwise).
+/// With `--long` every tag gets its own `path,tag,value` line instead.
+/// With `--group-by camera` each camera model gets its own `exif_output.<model>.<ext>`.
+fn write_output(rows: &[&Row], opts: &ScanOptions) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let layout = output_layout(opts);
+    let ext = opts.format.extension();
+    WRITING.store(true, Ordering::SeqCst);
+    let Some(GroupBy::Camera) = opts.group_by else {
+        if let Some(size) = opts.chunk_rows {
+            return write_chunks(rows, opts, &layout, size);
+        }
+        let dest = if opts.stdout {
+            Destination::Stdout
+        } else {
+            let name = opts.output.clone().unwrap_or_else(|| format!("{OUTPUT_STEM}.{ext}").into());
+            Destination::File(output_path(opts, name))
+        };
+        let mut out = create_writer(&dest, &layout)?;
+        for row in rows.iter().take_while(|_| !INTERRUPTED.load(Ordering::SeqCst)) {
+            out.write_row(row)?;
+        }
+        let (dest, _) = out.finish()?;
+        status!("EXIF data written to {}", dest);
+        return Ok(written_files([dest]));
+    };
+
+    // Route each row to its group's writer, opening files as new groups show up
+    let mut groups: BTreeMap<String, Box<dyn RowWriter>> = BTreeMap::new();
+    for row in rows.iter().take_while(|_| !INTERRUPTED.load(Ordering::SeqCst)) {
+        let group = sanitize_file_component(row.model.as_deref().unwrap_or(""));
+        let out = match groups.entry(group) {
+            Entry::Occupied(slot) => slot.into_mut(),
+            Entry::Vacant(slot) => {
+                let path = output_path(opts, format!("{}.{}.{}", OUTPUT_STEM, slot.key(), ext).into());
+                slot.insert(create_writer(&Destination::File(path), &layout)?)
+            }
+        };
+        out.write_row(row)?;
+    }
+    status!("EXIF data written to {} files, one per camera model:", groups.len());
+    let mut written = Vec::with_capacity(groups.len());
+    for out in groups.into_values() {
+        let (dest, count) = out.finish()?;
+        status!("  {}: {} rows", dest, count);
+        written.push(dest);
+    }
+    Ok(written_files(written))
+}
+
+/// The files among `written`, for `--on-complete`.
+fn written_files(written: impl IntoIterator<Item = Destination>) -> Vec<PathBuf> {
+    written
+        .into_iter()
+        .filter_map(|dest| match dest {
+            Destination::File(path) | Destination::Append(path) => Some(path),
+            Destination::Stdout => None,
         })
         .collect()
 }
 
-/// Extract EXIF data for one file, returning a row of strings for CSV.
-/// Logs and skips files whose EXIF cannot be read.
-fn extract_exif(file_path: &PathBuf) -> Option<Vec<String>> {
-    let path_str = file_path.to_string_lossy().into_owned(); // keep a String for the CSV row
-    match parse_file(file_path) {
-        Ok(exif) => {
-            let mut row = Vec::new();
-            row.push(path_str);                        // full path / filename
-            row.push(exif.mime.to_string());           // MIME type
-            row.push(exif.entries.len().to_string());  // # of tags
-            for entry in &exif.entries {
-                row.push(format!("{}: {}", entry.tag, entry.value_more_readable));
+/// Run the `--on-complete` command once per output file: split at spaces (no
+/// shell involved), with `{}` replaced by the file's path, or the path added as
+/// the last argument when there is no `{}`. Returns a line per run for the
+/// summary; the command's own output goes straight to the terminal.
+fn run_on_complete(command: &str, outputs: &[PathBuf]) -> Vec<String> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let placeholder = words.iter().any(|word| word.contains("{}"));
+    outputs
+        .iter()
+        .map(|output| {
+            let path = output.to_string_lossy();
+            let mut args: Vec<String> = words.iter().map(|word| word.replace("{}", &path)).collect();
+            if !placeholder {
+                args.push(path.into_owned());
             }
-            Some(row)
+            let ran = process::Command::new(&args[0]).args(&args[1..]).status();
+            match ran {
+                Ok(status) => {
+                    let outcome = if status.success() { "succeeded" } else { "failed" };
+                    format!("--on-complete for {}: {} {} ({})", output.display(), words[0], outcome, status)
+                }
+                Err(e) => {
+                    let message = format!("can't run {}: {}", words[0], e);
+                    report_error(&format!("--on-complete for {}: {}", output.display(), message), "on-complete", Some(output), &message);
+                    format!("--on-complete for {}: {}", output.display(), message)
+                }
+            }
+        })
+        .collect()
+}
+
+/// `--chunk-rows`: the output as numbered parts of at most `size` rows, each a
+/// complete file with its own header, closed before the next one is opened.
+fn write_chunks(rows: &[&Row], opts: &ScanOptions, layout: &Layout, size: usize) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let ext = opts.format.extension();
+    let name = opts.output.clone().unwrap_or_else(|| format!("{OUTPUT_STEM}.{ext}").into());
+    // no rows still makes one part, holding just the header
+    let chunks: Vec<&[&Row]> = if rows.is_empty() { vec![&[]] } else { rows.chunks(size).collect() };
+    let mut parts = Vec::with_capacity(chunks.len());
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            break;
         }
-        Err(e) => {
-            eprintln!("Failed to parse EXIF in {}: {}", path_str, e);
-            None
+        let dest = Destination::File(output_path(opts, part_name(&name, i + 1)));
+        let mut out = create_writer(&dest, layout)?;
+        for row in chunk.iter().take_while(|_| !INTERRUPTED.load(Ordering::SeqCst)) {
+            out.write_row(row)?;
         }
+        parts.push(out.finish()?);
+    }
+    status!("EXIF data written to {} parts of up to {} rows:", parts.len(), thousands(size));
+    for (dest, count) in &parts {
+        status!("  {}: {} rows", dest, count);
     }
+    Ok(written_files(parts.into_iter().map(|(dest, _)| dest)))
 }
 
-/// Write all rows to `exif_output.csv`, with a timestamp header line.
-fn to_csv(rows: &[Vec<String>]) -> Result<(), Box<dyn Error>> {
-    let now = Local::now();
-    let mut wtr = WriterBuilder::new()
-        .flexible(true)
-        .from_path("exif_output.csv")?;
+/// `exif_output.csv` → `exif_output.part003.csv` (a `.gz` stays last).
+fn part_name(name: &Path, part: usize) -> PathBuf {
+    let file = name.file_name().unwrap_or_default().to_string_lossy();
+    let (file, gz) = match file.strip_suffix(".gz") {
+        Some(file) => (file, ".gz"),
+        None => (&*file, ""),
+    };
+    let part_file = match file.rsplit_once('.') {
+        Some((stem, ext)) => format!("{stem}.part{part:03}.{ext}{gz}"),
+        None => format!("{file}.part{part:03}{gz}"),
+    };
+    name.with_file_name(part_file)
+}
 
-    // Comment‑style timestamp row (many CSV readers ignore lines that start with '#')
-    wtr.write_record(&[format!("# csv_created_at: {}", now.to_rfc3339())])?;
+/// `name` with `.gz` added when the output is compressed and it doesn't end in `.gz` already.
+fn output_path(opts: &ScanOptions, name: PathBuf) -> PathBuf {
+    if !opts.compressed() || name.extension().is_some_and(|e| e == "gz") {
+        return name;
+    }
+    let mut name = name.into_os_string();
+    name.push(".gz");
+    name.into()
+}
 
+/// Write the `--path-map` sidecar: each row's hashed path next to the real one.
+fn write_path_map(path: &Path, rows: &[&Row], style: PathStyle) -> Result<(), Box<dyn Error>> {
+    let mut wtr = WriterBuilder::new().from_path(path)?;
+    wtr.write_record(["hashed_path", "path"])?;
     for row in rows {
-        wtr.write_record(row)?;
+        wtr.write_record([style.show(&row.path), row.path.to_string_lossy().into_owned()])?;
     }
     wtr.flush()?;
-    println!("EXIF data written to exif_output.csv");
+    Ok(())
+}
 
+/// Write one row per failed file to `path`: path, category, message, size in bytes.
+fn write_error_log(path: &Path, failures: &[ExtractError]) -> Result<(), Box<dyn Error>> {
+    let mut wtr = WriterBuilder::new().from_path(path)?;
+    wtr.write_record(["path", "category", "message", "file_size"])?;
+    for failure in failures {
+        wtr.write_record([
+            failure.path.to_string_lossy().into_owned(),
+            failure.category.as_str().to_string(),
+            failure.message.clone(),
+            failure.file_size.map(|n| n.to_string()).unwrap_or_default(),
+        ])?;
+    }
+    wtr.flush()?;
     Ok(())
-    
 }
 
-fn main() {
-    // Collect .jpeg and .jpg files from the current directory
-    let mut files = find_files_by_extension(".", "jpeg");
-    files.extend(find_files_by_extension(".", "jpg"));
-    files.sort(); // deterministic ordering
-
-    // Parallel EXIF extraction
-    let exif_rows: Vec<Vec<String>> = files
-        .par_iter()
-        .filter_map(extract_exif)
+/// One `--manifest` line per processed file: (path, status, detail, bytes, mtime),
+/// sorted by path. Status is `ok`, `empty` (parsed, but no tags), `skipped`
+/// (with the reason as detail) or `failed` (with the error).
+fn manifest_entries(rows: &[Row], failures: &[ExtractError], style: PathStyle) -> Vec<[String; 5]> {
+    let modified = |path: &Path| {
+        organize::file_mtime(path)
+            .map(|t| t.format("%Y-%m-%dT%H:%M:%S").to_string())
+            .unwrap_or_default()
+    };
+    let size = |size: Option<u64>| size.map(|n| n.to_string()).unwrap_or_default();
+    let mut entries: Vec<(&Path, [String; 5])> = rows
+        .iter()
+        .map(|row| {
+            let (status, detail) = match &row.error {
+                Some(reason) => ("skipped", reason.strip_prefix("skipped: ").unwrap_or(reason).to_string()),
+                None if row.tags.is_empty() => ("empty", String::new()),
+                None => ("ok", String::new()),
+            };
+            let line = [style.show(&row.path), status.to_string(), detail, size(row.file_size), modified(&row.path)];
+            (row.path.as_path(), line)
+        })
+        .chain(failures.iter().map(|failure| {
+            let detail = format!("{}: {}", failure.category.as_str(), failure.message);
+            let path = &failure.path;
+            let line = [style.show(path), "failed".to_string(), detail, size(failure.file_size), modified(path)];
+            (path.as_path(), line)
+        }))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries.into_iter().map(|(_, line)| line).collect()
+}
+
+/// Header of the `--manifest` CSV, and the keys of its JSON records.
+const MANIFEST_HEADER: [&str; 5] = ["path", "status", "detail", "file_size", "modified"];
+
+/// Write the `--manifest`: CSV after a `# manifest_created_at` line, or one JSON
+/// document when the name ends in `.json` (blank cells as `null`, as in JSON Lines).
+fn write_manifest(path: &Path, entries: &[[String; 5]], created_at: &str) -> Result<(), Box<dyn Error>> {
+    if has_extension(path, &["json"]) {
+        let files: Vec<serde_json::Value> = entries
+            .iter()
+            .map(|line| {
+                let record = MANIFEST_HEADER.iter().zip(line);
+                let record = record.map(|(k, v)| {
+                    let value = if v.is_empty() { serde_json::Value::Null } else { v.as_str().into() };
+                    (k.to_string(), value)
+                });
+                serde_json::Value::Object(record.collect())
+            })
+            .collect();
+        let doc = serde_json::json!({ "created_at": created_at, "files": files });
+        fs::write(path, serde_json::to_string_pretty(&doc)? + "\n")?;
+        return Ok(());
+    }
+    let mut wtr = WriterBuilder::new().flexible(true).from_path(path)?;
+    wtr.write_record([format!("# manifest_created_at: {created_at}")])?;
+    wtr.write_record(MANIFEST_HEADER)?;
+    for line in entries {
+        wtr.write_record(line)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Totals of one folder for `--rollup`.
+#[derive(Default)]
+struct Rollup<'a> {
+    files: usize,
+    with_exif: usize,
+    failed: usize, // extraction failures; skipped files count in `files` only
+    bytes: u64,
+    earliest: Option<DateTime<FixedOffset>>, // DateTimeOriginal as moments, as --sort-by date compares them
+    latest: Option<DateTime<FixedOffset>>,
+    models: BTreeSet<&'a str>,
+}
+
+impl<'a> Rollup<'a> {
+    /// Count one file: its row, or `None` when extraction failed.
+    fn add(&mut self, size: Option<u64>, row: Option<&'a Row>) {
+        self.files += 1;
+        self.bytes += size.unwrap_or(0);
+        let Some(row) = row else {
+            self.failed += 1;
+            return;
+        };
+        if row.error.is_some() {
+            return;
+        }
+        self.with_exif += 1;
+        if let Some(taken) = row.taken_at() {
+            self.earliest = Some(self.earliest.map_or(taken, |t| t.min(taken)));
+            self.latest = Some(self.latest.map_or(taken, |t| t.max(taken)));
+        }
+        self.models.extend(row.model.as_deref());
+    }
+}
+
+/// `--rollup`: the processed files totalled per folder, sorted by folder. Each
+/// file counts toward its own folder and, with `--rollup-depth n`, toward the
+/// n folders above it, so a line can cover a whole subtree.
+fn rollup<'a>(rows: &'a [Row], failures: &[ExtractError], depth: usize) -> BTreeMap<PathBuf, Rollup<'a>> {
+    let mut folders: BTreeMap<PathBuf, Rollup> = BTreeMap::new();
+    let mut add = |path: &Path, size: Option<u64>, row: Option<&'a Row>| {
+        let folder = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        for dir in folder.ancestors().filter(|d| !d.as_os_str().is_empty()).take(depth + 1) {
+            folders.entry(dir.to_path_buf()).or_default().add(size, row);
+        }
+    };
+    for row in rows {
+        add(&row.path, row.file_size, Some(row));
+    }
+    for failure in failures {
+        add(&failure.path, failure.file_size, None);
+    }
+    folders
+}
+
+/// Write the `--rollup` CSV after a `# rollup_created_at` line; camera models
+/// are joined by `;`, and the dates are blank for folders without any.
+fn write_rollup(path: &Path, folders: &BTreeMap<PathBuf, Rollup>, style: PathStyle, created_at: &str) -> Result<(), Box<dyn Error>> {
+    let date = |t: Option<DateTime<FixedOffset>>| t.map(|t| t.format("%Y-%m-%dT%H:%M:%S%:z").to_string()).unwrap_or_default();
+    let mut wtr = WriterBuilder::new().flexible(true).from_path(path)?;
+    wtr.write_record([format!("# rollup_created_at: {created_at}")])?;
+    wtr.write_record(["directory", "files", "with_exif", "failed", "total_bytes", "earliest", "latest", "camera_models"])?;
+    for (dir, totals) in folders {
+        wtr.write_record([
+            style.show(dir),
+            totals.files.to_string(),
+            totals.with_exif.to_string(),
+            totals.failed.to_string(),
+            totals.bytes.to_string(),
+            date(totals.earliest),
+            date(totals.latest),
+            totals.models.iter().copied().collect::<Vec<_>>().join(";"),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// `n` with thousands separators: 1204 → "1,204".
+fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Fill in each parsed row's `missing_tags`: the `required` tags it doesn't have,
+/// spelled as the user gave them. Rows skipped without parsing are left alone.
+fn audit_tags(rows: &mut [Row], required: &[String]) {
+    for row in rows.iter_mut().filter(|row| row.error.is_none()) {
+        let missing = required.iter().filter(|t| !row.has_tag(t)).cloned().collect();
+        row.missing_tags = Some(missing);
+    }
+}
+
+/// Fill in each dated row's `date_drift`: how far the file's mtime is from its
+/// EXIF capture date, and whether that is more than `threshold` seconds.
+fn check_date_drift(rows: &mut [Row], threshold: u64) {
+    for row in rows.iter_mut() {
+        let Some(taken) = row.date_taken else { continue };
+        row.date_drift = organize::date_drift(taken, &row.path).map(|drift| (drift, drift.unsigned_abs() > threshold));
+    }
+}
+
+/// A number of seconds in the largest unit that divides it: `2h`, `90m`, `45s`.
+fn short_duration(seconds: u64) -> String {
+    match seconds {
+        0 => "0s".to_string(),
+        s if s.is_multiple_of(86_400) => format!("{}d", s / 86_400),
+        s if s.is_multiple_of(3_600) => format!("{}h", s / 3_600),
+        s if s.is_multiple_of(60) => format!("{}m", s / 60),
+        s => format!("{s}s"),
+    }
+}
+
+/// A signed drift for people: `+3.5 days`, `-2.0 hours`, `+45 s`.
+fn drift_label(seconds: i64) -> String {
+    match seconds.unsigned_abs() {
+        s if s >= 86_400 => format!("{:+.1} days", seconds as f64 / 86_400.0),
+        s if s >= 3_600 => format!("{:+.1} hours", seconds as f64 / 3_600.0),
+        _ => format!("{seconds:+} s"),
+    }
+}
+
+/// Turn extraction results into rows, cleaned up and with `--assume-offset`
+/// filled in, and the failures, each reported as it's met.
+fn split_results(results: Vec<(Result<Row, ExtractError>, Option<Duration>)>, opts: &ScanOptions) -> (Vec<Row>, Vec<ExtractError>) {
+    let mut rows = Vec::with_capacity(results.len());
+    let mut failures = Vec::new();
+    let sanitize = opts.sanitize();
+    let assumed_offset = opts.offset.as_deref().and_then(fields::parse_offset);
+    for (result, _) in results {
+        match result {
+            Ok(mut row) => {
+                row.sanitize(sanitize);
+                if row.offset.is_none() && assumed_offset.is_some() {
+                    row.offset = assumed_offset;
+                    row.offset_assumed = true;
+                }
+                rows.push(row);
+            }
+            Err(e) => {
+                report_error(&format!("Failed to extract EXIF from {}", e), e.category.as_str(), Some(&e.path), &e.message);
+                failures.push(e);
+            }
+        }
+    }
+    (rows, failures)
+}
+
+/// The output row of a file that failed: only its path and size, with `error`
+/// set to `<category>: <message>`.
+fn failure_row(e: &ExtractError) -> Row {
+    let mut row = Row::skipped(e.path.clone(), &format!("{}: {}", e.category.as_str(), e.message));
+    row.file_size = e.file_size;
+    row
+}
+
+/// `rows` and `failed` (the rows of the files that failed) merged back into
+/// the order of `files`, which both follow.
+fn in_file_order<'a>(files: &[PathBuf], rows: &'a [Row], failed: &'a [Row]) -> Vec<&'a Row> {
+    let (mut rows, mut failed) = (rows.iter(), failed.iter().peekable());
+    files
+        .iter()
+        .filter_map(|file| match failed.peek() {
+            Some(row) if row.path == *file => failed.next(),
+            _ => rows.next(),
+        })
+        .collect()
+}
+
+/// Check for the metadata the user relies on (`--require-tags`, `--date-drift`).
+fn check_rows(rows: &mut [Row], opts: &ScanOptions) {
+    if !opts.require_tags.is_empty() {
+        audit_tags(rows, &opts.require_tags);
+    }
+    if let Some(threshold) = opts.date_drift {
+        check_date_drift(rows, threshold);
+    }
+}
+
+/// Whether `row` goes into the output, after `--only-missing`, `--min-tags`
+/// and the `--within` / `--bbox` area.
+fn is_shown(row: &Row, opts: &ScanOptions) -> bool {
+    passes_tag_filters(row, opts) && !outside_area(row, opts)
+}
+
+/// Whether `row` gets past `--only-missing` and `--min-tags`.
+fn passes_tag_filters(row: &Row, opts: &ScanOptions) -> bool {
+    let flagged = row.missing_tags.as_ref().is_some_and(|m| !m.is_empty());
+    (flagged || !opts.only_missing) && !too_few_tags(row, opts.min_tags)
+}
+
+/// Whether the `--within` / `--bbox` area leaves `row` out: taken elsewhere,
+/// or without GPS unless `--include-no-gps`.
+fn outside_area(row: &Row, opts: &ScanOptions) -> bool {
+    let Some(area) = opts.geofence() else { return false };
+    match row.gps {
+        Some(position) => !area.contains(position),
+        None => !opts.include_no_gps,
+    }
+}
+
+/// Files between two saves of `--checkpoint`, unless `--checkpoint-every` says otherwise.
+const DEFAULT_CHECKPOINT_EVERY: usize = 1000;
+/// Files parsed between two looks at the clock for `--checkpoint-interval`.
+const INTERVAL_STEP: usize = 64;
+
+/// `--checkpoint` / `--resume`: extract and append the output one batch of
+/// files at a time, saving after each batch how far the run got, so an
+/// interrupted run goes on where it stopped.
+fn run_checkpointed(
+    files: &[PathBuf],
+    opts: &ScanOptions,
+    extract_one: impl Fn(&PathBuf) -> (Result<Row, ExtractError>, Option<Duration>) + Sync,
+    timings: &mut Timings,
+) -> Result<Session, Box<dyn Error>> {
+    let save_to = opts.checkpoint.as_ref().or(opts.resume.as_ref()).expect("only called with one of them");
+    let options = opts.checkpoint_options();
+    let layout = output_layout(opts);
+    let mut state = match &opts.resume {
+        Some(resume) => {
+            let state = Checkpoint::load(resume)?;
+            if let Some(why) = state.mismatch(&options, files) {
+                return Err(format!(
+                    "Can't resume from {}: {}. Re-run with the same options, or start over without --resume",
+                    resume.display(),
+                    why
+                )
+                .into());
+            }
+            // a crash may have left part of a batch after the checkpointed length
+            let partial = fs::File::options()
+                .write(true)
+                .open(&state.output)
+                .map_err(|e| format!("Can't reopen the partial output {}: {}", state.output.display(), e))?;
+            partial.set_len(state.output_bytes)?;
+            status!("Resuming from {}: {} of {} files were done already", resume.display(), state.completed, state.files);
+            state
+        }
+        None => {
+            let ext = opts.format.extension();
+            let output = output_path(opts, opts.output.clone().unwrap_or_else(|| format!("{OUTPUT_STEM}.{ext}").into()));
+            create_writer(&Destination::File(output.clone()), &layout)?.finish()?;
+            Checkpoint {
+                options,
+                files: files.len(),
+                file_list: file_list_hash(files),
+                completed: 0,
+                output_bytes: fs::metadata(&output)?.len(),
+                output,
+            }
+        }
+    };
+    state.save(save_to)?;
+    let done_before = state.completed;
+    let (mut rows, mut failures) = (Vec::new(), Vec::new());
+    let every = opts.every.unwrap_or(DEFAULT_CHECKPOINT_EVERY);
+    let interval = opts.interval.map(Duration::from_secs);
+    // with --checkpoint-interval the files are parsed a few at a time, so the
+    // clock is looked at often enough
+    let step = if interval.is_some() { every.min(INTERVAL_STEP) } else { every };
+    let mut last_save = Instant::now();
+    let mut results = Vec::new(); // of the batch being gathered, which starts at state.completed
+    while state.completed + results.len() < files.len() {
+        let from = state.completed + results.len();
+        let to = (from + step).min(state.completed + every).min(files.len());
+        results.extend(in_order(&files[from..to], &extract_one));
+        let due = to == state.completed + every || to == files.len() || interval.is_some_and(|i| last_save.elapsed() >= i);
+        if !due {
+            continue;
+        }
+        let batch = &files[state.completed..to];
+        let results = std::mem::take(&mut results);
+        timings.add_files(
+            batch
+                .iter()
+                .zip(&results)
+                .filter_map(|(file, (_, took))| Some((file.clone(), (*took)?))),
+        );
+        let (mut batch_rows, batch_failures) = split_results(results, opts);
+        check_rows(&mut batch_rows, opts);
+        let failed_rows: Vec<Row> = batch_failures.iter().map(failure_row).collect();
+        let mut out = create_writer(&Destination::Append(state.output.clone()), &layout)?;
+        for row in in_file_order(batch, &batch_rows, &failed_rows).into_iter().filter(|row| is_shown(row, opts)) {
+            out.write_row(row)?;
+        }
+        out.finish()?;
+        state.completed = to;
+        state.output_bytes = fs::metadata(&state.output)?.len();
+        state.save(save_to)?;
+        last_save = Instant::now();
+        rows.extend(batch_rows);
+        let failed_fast = opts.fail_fast && !batch_failures.is_empty();
+        failures.extend(batch_failures);
+        if failed_fast {
+            // the batch is written and saved, so no more are started
+            return Ok(Session { done_before, rows, failures, output: state.output, failed_fast });
+        }
+    }
+    status!("EXIF data written to {}; all {} files are done", state.output.display(), state.files);
+    Ok(Session { done_before, rows, failures, output: state.output, failed_fast: false })
+}
+
+/// What one session of a `--checkpoint` run did.
+struct Session {
+    done_before: usize, // files a resumed run found done already
+    rows: Vec<Row>,     // rows of the files after those
+    output: PathBuf,    // the file the rows went to, for --on-complete
+    failures: Vec<ExtractError>,
+    failed_fast: bool,  // --fail-fast stopped it after the batch with the first failure
+}
+
+/// `--fail-fast`: report the first of `failures` (in file order) as what stopped
+/// the run after `read` of `total` files, log them all to `--error-log`, and
+/// exit with status 1. `written` says what became of the output.
+fn stop_fast(failures: Vec<ExtractError>, read: usize, total: usize, written: &str, opts: &ScanOptions) -> ! {
+    let e = &failures[0];
+    let prose = format!(
+        "\nFAILED: {}\n  {}: {}\nStopped by --fail-fast after reading {} of {} files; {}",
+        e.path.display(),
+        e.category.as_str(),
+        e.message,
+        read,
+        total,
+        written
+    );
+    report_error(&prose, e.category.as_str(), Some(&e.path), &e.message);
+    if let Some(log) = &opts.error_log {
+        if let Err(e) = write_error_log(log, &failures) {
+            report_error(&format!("Error writing error log {}: {}", log.display(), e), "output", Some(log), &e.to_string());
+        }
+    }
+    process::exit(1);
+}
+
+/// Whether `--min-tags` leaves `row` out; rows that weren't parsed (skipped files) stay.
+fn too_few_tags(row: &Row, min_tags: usize) -> bool {
+    row.error.is_none() && row.tags.len() < min_tags
+}
+
+/// End-of-run counts; failures point at the error log when one was written.
+/// `found` counts every discovered file, including those cut off by `--limit`
+/// (with `--sample`, every sampled one); `sample` is the population and seed.
+fn print_summary(found: usize, sample: Option<(usize, u64)>, rows: &[Row], failures: &[ExtractError], opts: &ScanOptions) {
+    let too_large = rows
+        .iter()
+        .filter(|row| row.error.as_deref() == Some(SKIPPED_TOO_LARGE))
+        .count();
+    let not_files = rows
+        .iter()
+        .filter(|row| matches!(row.error.as_deref(), Some(SKIPPED_MISSING | SKIPPED_NOT_A_FILE)))
+        .count();
+    status!(
+        "{} files found, {} with EXIF, {} skipped as too large, {} failed",
+        found,
+        rows.iter().filter(|row| row.error.is_none()).count(),
+        too_large,
+        failures.len()
+    );
+    if let Some((population, seed)) = sample {
+        status!(
+            "Random sample of {} out of {} files; --sample {} --seed {} picks the same ones again",
+            thousands(found),
+            thousands(population),
+            opts.sample.unwrap_or(found),
+            seed
+        );
+    }
+    if !failures.is_empty() {
+        // unreadable files point at the disk or the network, broken EXIF at the files
+        let failed = |category| failures.iter().filter(|f| f.category == category).count();
+        let io = failed(ErrorCategory::Io);
+        status!(
+            "  failed: {} unreadable (io), {} with broken EXIF (exif-parse), {} not JPEG/TIFF (unsupported-format)",
+            io,
+            failed(ErrorCategory::ExifParse),
+            failed(ErrorCategory::UnsupportedFormat)
+        );
+        if io > 0 && opts.retries == 0 {
+            status!("Read errors on network shares are often transient; --retries <n> reads such files again");
+        }
+    }
+    if not_files > 0 {
+        status!("{} paths from --files-from are missing or not regular files", not_files);
+    }
+    let no_reader = rows
+        .iter()
+        .filter(|row| row.error.as_deref() == Some(SKIPPED_NO_READER))
+        .count();
+    if no_reader > 0 {
+        status!("{} PNG/HEIC files found by --sniff were not parsed (EXIF is only read from JPEG and TIFF)", no_reader);
+    }
+    let sparse = rows.iter().filter(|row| too_few_tags(row, opts.min_tags)).count();
+    if sparse > 0 {
+        status!("{} files with fewer than {} EXIF tags were left out (--min-tags)", sparse, opts.min_tags);
+    }
+    // only the rows the area itself dropped; the ones above are gone already
+    let outside: Vec<&Row> = rows
+        .iter()
+        .filter(|row| passes_tag_filters(row, opts) && outside_area(row, opts))
+        .collect();
+    if !outside.is_empty() {
+        let flag = if opts.within.is_some() { "--within" } else { "--bbox" };
+        let no_gps = outside.iter().filter(|row| row.gps.is_none()).count();
+        if opts.include_no_gps {
+            status!("{} files taken outside the area were left out ({})", outside.len(), flag);
+        } else {
+            status!("{} files outside the area were left out ({}), {} of them without GPS", outside.len(), flag, no_gps);
+        }
+    }
+    let processed = rows.len() + failures.len();
+    if processed < found {
+        status!("Run truncated by --limit: only the first {} of {} files were processed", processed, found);
+    }
+    // Name the oversized files: a big one is usually a stray video or scan worth a look
+    const MAX_LISTED: usize = 10;
+    let oversized = rows
+        .iter()
+        .filter(|row| row.error.as_deref() == Some(SKIPPED_TOO_LARGE));
+    for row in oversized.take(MAX_LISTED) {
+        let size = fs::metadata(&row.path).map(|m| m.len()).unwrap_or_default();
+        status!("  too large: {} ({} bytes)", row.path.display(), size);
+    }
+    if too_large > MAX_LISTED {
+        status!("  … and {} more", too_large - MAX_LISTED);
+    }
+    let mislabeled: Vec<&Row> = rows.iter().filter(|row| row.mime_mismatch() == Some(true)).collect();
+    if !mislabeled.is_empty() {
+        status!("Warning: {} files have an extension that doesn't match their content:", mislabeled.len());
+        for row in mislabeled.iter().take(MAX_LISTED) {
+            status!("  {} is {}", row.path.display(), row.mime);
+        }
+        if mislabeled.len() > MAX_LISTED {
+            status!("  … and {} more", mislabeled.len() - MAX_LISTED);
+        }
+    }
+    for wanted in &opts.require_tags {
+        let missing = rows
+            .iter()
+            .filter(|row| row.missing_tags.as_ref().is_some_and(|m| m.contains(wanted)))
+            .count();
+        status!("{} files missing {}", thousands(missing), wanted);
+    }
+    if let Some(threshold) = opts.date_drift {
+        let drifted: Vec<&Row> = rows.iter().filter(|row| row.date_drift.is_some_and(|(_, d)| d)).collect();
+        status!(
+            "{} files modified more than {} away from their EXIF capture date",
+            thousands(drifted.len()),
+            short_duration(threshold)
+        );
+        for row in drifted.iter().take(MAX_LISTED) {
+            let (seconds, _) = row.date_drift.unwrap_or_default();
+            status!("  drifted: {} ({})", row.path.display(), drift_label(seconds));
+        }
+        if drifted.len() > MAX_LISTED {
+            status!("  … and {} more", drifted.len() - MAX_LISTED);
+        }
+    }
+    if failures.is_empty() {
+        return;
+    }
+    match &opts.error_log {
+        Some(log) => match write_error_log(log, failures) {
+            Ok(()) => status!("Details of the failed files written to {}", log.display()),
+            Err(e) => report_error(&format!("Error writing error log {}: {}", log.display(), e), "output", Some(log), &e.to_string()),
+        },
+        None => status!("Re-run with --error-log <file> to keep a record of the failures"),
+    }
+}
+
+/// Files listed as the slowest by `--timing`.
+const SLOWEST_LISTED: usize = 10;
+
+/// `--preview`: a table sized to the terminal that shows messages (stderr under
+/// `--stdout`), plain tab-separated lines when its width is unknown.
+fn print_preview(rows: &[&Row], data_on_stdout: bool) {
+    let size = if data_on_stdout {
+        terminal_size_of(io::stderr())
+    } else {
+        terminal_size_of(io::stdout())
+    };
+    let width = size.map(|(Width(w), _)| usize::from(w));
+    status!("First {} records:", rows.len());
+    let table = preview_table(rows, width);
+    status!("{}", table.trim_end());
+}
+
+/// `--timing` goes to stderr, `--timing-json` to its file.
+fn report_timings(timings: &Timings, opts: &ScanOptions) {
+    if opts.timing {
+        eprint!("{}", timings.report(SLOWEST_LISTED));
+    }
+    if let Some(path) = &opts.timing_json {
+        let json = timings.to_json(SLOWEST_LISTED).to_string();
+        match fs::write(path, json + "\n") {
+            Ok(()) => status!("Timings written to {}", path.display()),
+            Err(e) => report_error(&format!("Error writing timings {}: {}", path.display(), e), "output", Some(path), &e.to_string()),
+        }
+    }
+}
+
+/// Resolve `file` to an absolute path for `--absolute-paths`.
+/// A path that can't be resolved (e.g. a broken symlink) is kept as is, with a warning.
+fn absolute_path(file: PathBuf) -> PathBuf {
+    match fs::canonicalize(&file) {
+        Ok(abs) => abs,
+        Err(e) => {
+            eprintln!("Warning: could not resolve {} ({}), keeping the path as is", file.display(), e);
+            file
+        }
+    }
+}
+
+/// Size of `file` when `--max-size` is set and the file is bigger than it.
+fn oversized(file: &Path, max_size: u64) -> Option<u64> {
+    if max_size == 0 {
+        return None;
+    }
+    let size = fs::metadata(file).ok()?.len();
+    (size > max_size).then_some(size)
+}
+
+/// Copy/move every discovered file into dated folders under `dest` and note the
+/// outcome on its row. Files without an EXIF date go by their modification time
+/// unless `--require-exif-date`; files whose EXIF failed to parse have no row but
+/// are filed the same way.
+fn organize_files(files: &[PathBuf], rows: &mut [Row], opts: &ScanOptions, dest: &Path) {
+    let mut organizer = organize::Organizer::new(organize::OrganizeOptions {
+        dest: dest.to_path_buf(),
+        layout: opts.layout.clone().unwrap_or_else(|| organize::DEFAULT_LAYOUT.to_string()),
+        move_files: opts.move_files,
+        dry_run: opts.dry_run,
+    });
+    let verb = match (opts.dry_run, opts.move_files) {
+        (false, false) => "copied",
+        (false, true) => "moved",
+        (true, false) => "would copy",
+        (true, true) => "would move",
+    };
+    let mut by_path: HashMap<PathBuf, &mut Row> = rows.iter_mut().map(|row| (row.path.clone(), row)).collect();
+
+    let mut done = 0;
+    let mut undated = 0;
+    for file in files {
+        let mut row = by_path.remove(file);
+        let exif_date = row.as_ref().and_then(|row| row.date_taken);
+        let (date, from_mtime) = match exif_date {
+            Some(date) => (Some(date), false),
+            None if opts.require_exif_date => {
+                undated += 1;
+                if let Some(row) = row.as_mut() {
+                    row.organize_action = Some("skipped: no EXIF date".to_string());
+                }
+                continue;
+            }
+            None => (organize::file_mtime(file), true),
+        };
+        let action = match organizer.organize_file(file, date) {
+            Ok(target) => {
+                done += 1;
+                if opts.dry_run {
+                    status!("{} {} -> {}", verb, file.display(), target.display());
+                }
+                if let Some(row) = row.as_mut() {
+                    row.organized_path = Some(target);
+                }
+                if from_mtime {
+                    format!("{verb} (by file time)")
+                } else {
+                    verb.to_string()
+                }
+            }
+            Err(e) => {
+                report_error(&format!("Failed to organize {}: {}", file.display(), e), "organize", Some(file), &e.to_string());
+                format!("failed: {e}")
+            }
+        };
+        if let Some(row) = row.as_mut() {
+            row.organize_action = Some(action);
+        }
+    }
+    let done_verb = if opts.move_files { "moved" } else { "copied" };
+    let would = if opts.dry_run { "would be " } else { "" };
+    status!("{} of {} files {}{} into {}", done, files.len(), would, done_verb, dest.display());
+    if undated > 0 {
+        status!("{} files without an EXIF date left where they are (--require-exif-date)", undated);
+    }
+}
+
+/// `--touch`: set the modification time of every parsed file with a usable
+/// DateTimeOriginal to that moment (see [`Row::taken_at`]), noting the old and
+/// new time and the outcome on its row; with `--dry-run` only report the plan.
+fn touch_files(rows: &mut [Row], dry_run: bool) {
+    let verb = if dry_run { "would touch" } else { "touched" };
+    let (mut touched, mut undated, mut read_only) = (0, 0, 0);
+    for row in rows.iter_mut().filter(|row| row.error.is_none()) {
+        let metadata = match fs::metadata(&row.path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                row.touch_action = Some(format!("failed: {e}"));
+                continue;
+            }
+        };
+        row.mtime_before = metadata.modified().ok().map(DateTime::<Utc>::from);
+        let Some(taken) = row.taken_at() else {
+            undated += 1;
+            row.touch_action = Some("skipped: no EXIF date".to_string());
+            continue;
+        };
+        if metadata.permissions().readonly() {
+            read_only += 1;
+            row.touch_action = Some("skipped: read-only".to_string());
+            continue;
+        }
+        let new = taken.with_timezone(&Utc);
+        row.mtime_after = Some(new);
+        if row.mtime_before.is_some_and(|old| old.timestamp() == new.timestamp()) {
+            row.touch_action = Some("unchanged".to_string());
+            continue;
+        }
+        let result = if dry_run {
+            status!("{} {} -> {}", verb, row.path.display(), new.format("%Y-%m-%d %H:%M:%S UTC"));
+            Ok(())
+        } else {
+            fs::File::options().write(true).open(&row.path).and_then(|file| file.set_modified(new.into()))
+        };
+        row.touch_action = Some(match result {
+            Ok(()) => {
+                touched += 1;
+                verb.to_string()
+            }
+            Err(e) => {
+                let prose = format!("Failed to set the modification time of {}: {}", row.path.display(), e);
+                report_error(&prose, "touch", Some(&row.path), &e.to_string());
+                row.mtime_after = None;
+                format!("failed: {e}")
+            }
+        });
+    }
+    let would = if dry_run { "would be " } else { "" };
+    status!("{} files {}given their EXIF capture time as modification time (--touch)", touched, would);
+    if undated + read_only > 0 {
+        status!("  skipped: {} without a usable EXIF date, {} read-only", undated, read_only);
+    }
+}
+
+/// Collect .jpeg and .jpg files from the current directory, or with --sniff
+/// every file whose first bytes say it is an image; plus RAW files with --raw,
+/// minus those with an --exclude-ext extension. Each file is listed once, in no
+/// particular order; main sorts the list. `find_files_by_extension` would hand
+/// back a sorted, de-duplicated list, but this also needs --sniff, --recursive
+/// and counts of the links it leaves out, so it lists the folder itself.
+fn discover_files(opts: &ScanOptions) -> Discovered {
+    if let Some(list) = &opts.files_from {
+        return Discovered {
+            files: listed_files(list, opts.null_separated),
+            unreadable: Vec::new(),
+            symlinks_skipped: 0,
+            duplicates: 0,
+        };
+    }
+    let mut unreadable = Vec::new();
+    let all = if opts.recursive {
+        let root = Path::new(".");
+        let mut walk = walk_files(root, opts.max_depth, opts.follow_symlinks);
+        // a sub-folder that can't be listed is warned about; the starting one ends the run
+        if let Some(i) = walk.unreadable.iter().position(|(dir, _)| dir == root) {
+            unlistable(walk.unreadable.swap_remove(i).1);
+        }
+        unreadable = walk.unreadable;
+        walk.files
+    } else {
+        find_all_files(".").unwrap_or_else(|e| unlistable(e))
+    };
+    let (mut files, mut to_sniff) = (Vec::new(), Vec::new());
+    for file in all {
+        match name_verdict(&file, opts) {
+            Some(true) => files.push(file),
+            Some(false) => {}
+            None => to_sniff.push(file),
+        }
+    }
+    files.extend(sniff::sniff_images(to_sniff));
+    let mut found = Discovered {
+        files,
+        unreadable,
+        symlinks_skipped: 0,
+        duplicates: 0,
+    };
+    if opts.follow_symlinks {
+        found.duplicates = dedupe_by_target(&mut found.files);
+    } else {
+        let before = found.files.len();
+        found.files.retain(|f| !is_symlink(f));
+        found.symlinks_skipped = before - found.files.len();
+    }
+    found
+}
+
+/// Print what discovery left out or couldn't read.
+fn report_discovered(found: &Discovered) {
+    for (dir, e) in &found.unreadable {
+        eprintln!("Warning: could not read folder {} ({}), its files are missing", dir.display(), e);
+    }
+    if found.symlinks_skipped > 0 {
+        status!("Skipped {} symbolic links (use --follow-symlinks to read them)", found.symlinks_skipped);
+    }
+    if found.duplicates > 0 {
+        status!("Skipped {} links to files that were already found", found.duplicates);
+    }
+}
+
+/// Whether a `--recursive` scan can hand each file to extraction as soon as
+/// the walk lists it ([`extract_as_listed`]). The rows come back by path, or
+/// by `--sort-by`, either way; only these options need the complete list
+/// before parsing starts: `--order-by`, `--limit`, `--sample`, `--count-only`,
+/// `--checkpoint` (which saves it), `--follow-symlinks` (to find links to the
+/// same file) and `--fail-fast` (to stop at the first failure in path order).
+fn streams_discovery(opts: &ScanOptions) -> bool {
+    opts.recursive
+        && opts.files_from.is_none()
+        && opts.order_by == OrderBy::Path
+        && opts.order == Order::Asc
+        && opts.limit.is_none()
+        && opts.sample.is_none()
+        && !opts.count_only
+        && opts.checkpoint.is_none()
+        && opts.resume.is_none()
+        && !opts.follow_symlinks
+        && !opts.fail_fast
+}
+
+/// Discovery and extraction at once: the walk sends each folder's files down
+/// a channel as it lists them, and the workers take them from there, keeping
+/// the files [`discover_files`] would keep. Returns what was found, with the
+/// files in no particular order, and the result for each of them.
+fn extract_as_listed<T: Send>(opts: &ScanOptions, extract_one: impl Fn(&PathBuf) -> T + Sync) -> (Discovered, Vec<T>) {
+    let root = Path::new(".");
+    let (sender, listed) = mpsc::channel();
+    let symlinks_skipped = AtomicUsize::new(0);
+    let keep = |file: &PathBuf| {
+        if !name_verdict(file, opts).unwrap_or_else(|| sniff::looks_like_image(file)) {
+            return false;
+        }
+        let link = is_symlink(file);
+        if link {
+            symlinks_skipped.fetch_add(1, Ordering::Relaxed);
+        }
+        !link
+    };
+    let (walked, done): (_, Vec<(PathBuf, T)>) = thread::scope(|scope| {
+        let walk = scope.spawn(|| stream_files(root, opts.max_depth, sender));
+        let done = listed
+            .into_iter()
+            .par_bridge()
+            .filter(keep)
+            .map(|file| if opts.absolute_paths { absolute_path(file) } else { file })
+            .map(|file| {
+                let result = extract_one(&file);
+                (file, result)
+            })
+            .collect();
+        (walk.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)), done)
+    });
+    let mut unreadable = walked.unwrap_or_else(|e| {
+        let prose = format!("Error: could not start the folder walk: {}", e);
+        report_error(&prose, "input", None, &e.to_string());
+        process::exit(1);
+    });
+    // a sub-folder that can't be listed is warned about; the starting one ends the run
+    if let Some(i) = unreadable.iter().position(|(dir, _)| dir == root) {
+        unlistable(unreadable.swap_remove(i).1);
+    }
+    let (files, results) = done.into_iter().unzip();
+    let symlinks_skipped = symlinks_skipped.into_inner();
+    (Discovered { files, unreadable, symlinks_skipped, duplicates: 0 }, results)
+}
+
+/// Whether discovery keeps `file`, judged by its name: `--exclude-ext` first,
+/// then RAW extensions with `--raw`, then `.jpg` / `.jpeg`. `None` when
+/// `--sniff` has to look inside (RAW containers vary too much to sniff, so
+/// those always go by their name).
+fn name_verdict(file: &Path, opts: &ScanOptions) -> Option<bool> {
+    let ext = file.extension();
+    if opts.exclude_ext.iter().any(|e| ext.is_some_and(|ext| ext.eq_ignore_ascii_case(e))) {
+        return Some(false);
+    }
+    // --raw is only accepted by builds with the `raw` feature
+    if opts.raw && has_extension(file, RAW_EXTENSIONS) {
+        return Some(true);
+    }
+    if opts.sniff {
+        return None;
+    }
+    Some(has_extension(file, &["jpg", "jpeg"]))
+}
+
+/// Files `--sample` hands to `--sniff` at once, to be checked in parallel.
+const SNIFF_BATCH: usize = 256;
+
+/// `--sample` straight off the folder listing: each file discovery would keep
+/// goes to the reservoir as it is listed, so the full list is never held. The
+/// draw follows the listing order, which is the same for an unchanged folder.
+/// Returns the sample and how many files it was drawn from, or `None` when the
+/// options need the complete list first (`--files-from`, `--follow-symlinks`,
+/// `--max-depth`, `--count-only`).
+fn discover_sample(opts: &ScanOptions, n: usize, seed: u64) -> Option<(Discovered, usize)> {
+    let needs_all = opts.files_from.is_some() || opts.follow_symlinks || opts.max_depth.is_some() || opts.count_only;
+    if needs_all {
+        return None;
+    }
+    let root = Path::new(".");
+    let listing: Box<dyn Iterator<Item = Result<PathBuf, (PathBuf, io::Error)>>> = if opts.recursive {
+        Box::new(lazy_walk(root))
+    } else {
+        let entries = fs::read_dir(root).unwrap_or_else(|e| unlistable(e));
+        Box::new(entries.filter_map(|entry| {
+            let path = entry.ok()?.path();
+            path.is_file().then_some(Ok(path))
+        }))
+    };
+    let mut found = Discovered {
+        files: Vec::new(),
+        unreadable: Vec::new(),
+        symlinks_skipped: 0,
+        duplicates: 0,
+    };
+    // (file, whether --sniff has to look inside it)
+    let mut candidates = listing.filter_map(|listed| {
+        let file = match listed {
+            Ok(file) => file,
+            // the starting folder is listed first, and ends the run when it can't be
+            Err((dir, e)) if dir == root => unlistable(e),
+            Err(unreadable) => {
+                found.unreadable.push(unreadable);
+                return None;
+            }
+        };
+        match name_verdict(&file, opts) {
+            Some(false) => None,
+            verdict => Some((file, verdict.is_none())),
+        }
+    });
+    // sniffed a batch at a time in parallel, as a full scan does, keeping the listing order
+    let batches = std::iter::from_fn(|| {
+        let batch: Vec<(PathBuf, bool)> = candidates.by_ref().take(SNIFF_BATCH).collect();
+        if batch.is_empty() {
+            return None;
+        }
+        let images = batch.into_par_iter().filter(|(file, sniff)| !sniff || sniff::looks_like_image(file));
+        Some(images.map(|(file, _)| file).collect::<Vec<_>>())
+    });
+    let kept = batches.flatten().filter(|file| {
+        let link = is_symlink(file);
+        found.symlinks_skipped += usize::from(link);
+        !link
+    });
+    let (files, population) = sample_files(kept, n, seed);
+    found.files = files;
+    Some((found, population))
+}
+
+/// Stop the run because the current folder can't be listed, rather than write
+/// an empty output as if there were no images in it.
+fn unlistable(e: io::Error) -> ! {
+    let dir = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let prose = format!("Error: could not read the folder {}: {}", dir.display(), e);
+    report_error(&prose, "input", Some(&dir), &e.to_string());
+    process::exit(2);
+}
+
+/// The paths named in the `--files-from` list (`-` = stdin), taken as they are:
+/// no extension or symlink filtering, and missing files fail on extraction.
+/// A list that can't be read ends the run.
+fn listed_files(list: &Path, null_separated: bool) -> Vec<PathBuf> {
+    let separator = if null_separated { b'\0' } else { b'\n' };
+    let files = if list == Path::new("-") {
+        read_file_list(io::stdin().lock(), separator)
+    } else {
+        fs::File::open(list).and_then(|f| read_file_list(f, separator))
+    };
+    files.unwrap_or_else(|e| {
+        report_error(&format!("Error reading the file list {}: {}", list.display(), e), "input", Some(list), &e.to_string());
+        process::exit(2);
+    })
+}
+
+/// Stable sort of the (path-sorted) rows for `--sort-by`, so ties stay in path
+/// order; rows without the key (no EXIF date, no model, size unknown) go last.
+fn sort_rows(rows: &mut [&Row], by: SortBy) {
+    fn missing_last<K: Ord>(a: Option<K>, b: Option<K>) -> std::cmp::Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        }
+    }
+    match by {
+        SortBy::Path => {}
+        // as moments in time, so a trip across time zones sorts right
+        SortBy::Date => rows.sort_by(|a, b| missing_last(a.taken_at(), b.taken_at())),
+        SortBy::Model => rows.sort_by(|a, b| missing_last(a.model.as_ref(), b.model.as_ref())),
+        SortBy::Size => rows.sort_by(|a, b| missing_last(a.file_size, b.file_size)),
+    }
+}
+
+/// `--sample`: `n` of `files` drawn uniformly at random by reservoir sampling,
+/// in their original order; all of them when there are no more than `n`. Only
+/// the sample is held, so `files` can come straight from a folder listing.
+/// Also returns how many files there were.
+fn sample_files(files: impl Iterator<Item = PathBuf>, n: usize, seed: u64) -> (Vec<PathBuf>, usize) {
+    let mut rng = SplitMix64(seed);
+    let mut reservoir: Vec<(usize, PathBuf)> = Vec::new();
+    let mut population = 0;
+    for (i, file) in files.enumerate() {
+        population += 1;
+        if i < n {
+            reservoir.push((i, file));
+        } else {
+            let j = rng.below(i + 1);
+            if j < n {
+                reservoir[j] = (i, file);
+            }
+        }
+    }
+    reservoir.sort_by_key(|&(i, _)| i);
+    (reservoir.into_iter().map(|(_, file)| file).collect(), population)
+}
+
+/// A seed for `--sample` without `--seed`: the clock mixed with the process ID.
+fn random_seed() -> u64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |t| t.as_nanos() as u64);
+    SplitMix64(nanos ^ u64::from(process::id())).next()
+}
+
+/// Small, fast PRNG (SplitMix64); plenty for picking files, and the same seed
+/// gives the same numbers on every platform.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..bound`, by multiply-shift (no division, unlike `%`).
+    fn below(&mut self, bound: usize) -> usize {
+        ((u128::from(self.next()) * bound as u128) >> 64) as usize
+    }
+}
+
+/// Put the path-sorted `files` in `--order-by` / `--order` order for extraction.
+fn processing_order(files: &mut Vec<PathBuf>, by: OrderBy, order: Order) {
+    match by {
+        OrderBy::Path if order == Order::Desc => files.reverse(),
+        OrderBy::Path => {}
+        OrderBy::Mtime => sort_by_metadata(files, |meta| meta.modified().ok(), order),
+        OrderBy::Size => sort_by_metadata(files, |meta| Some(meta.len()), order),
+    }
+}
+
+/// Stable sort on a key read from each file's metadata (read in parallel).
+/// Files without a key go last in either direction.
+fn sort_by_metadata<K: Ord + Send>(
+    files: &mut Vec<PathBuf>,
+    key: impl Fn(&fs::Metadata) -> Option<K> + Sync,
+    order: Order,
+) {
+    let mut keyed: Vec<(Option<K>, PathBuf)> = std::mem::take(files)
+        .into_par_iter()
+        .map(|file| (fs::metadata(&file).ok().and_then(|meta| key(&meta)), file))
         .collect();
+    keyed.sort_by(|(a, _), (b, _)| match (a, b) {
+        (Some(a), Some(b)) if order == Order::Desc => b.cmp(a),
+        (Some(a), Some(b)) => a.cmp(b),
+        _ => a.is_none().cmp(&b.is_none()),
+    });
+    *files = keyed.into_iter().map(|(_, file)| file).collect();
+}
+
+/// `f` over `files` on the worker pool, results in `files` order. Unlike
+/// `par_iter`, which gives each worker a range to start in, this hands the files
+/// out one after the other, so the first ones in the list are read first.
+fn in_order<T: Send>(files: &[PathBuf], f: impl Fn(&PathBuf) -> T + Sync) -> Vec<T> {
+    let mut done: Vec<(usize, T)> = files.iter().enumerate().par_bridge().map(|(i, file)| (i, f(file))).collect();
+    done.sort_by_key(|(i, _)| *i);
+    done.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Where `verify` writes the discrepancies unless `--output` says otherwise.
+const VERIFY_RESULT: &str = "verify_result.csv";
+
+/// `exif_etl verify <report> [--dir <folder>] [--max-depth <n>] [--output <file>]`: compare a
+/// report with the files as they are now (see [`verify::verify`]) and write a
+/// `path,status,detail` line per discrepancy. Returns the exit code: 0 without
+/// drift, 1 with some, 2 when the check couldn't run.
+fn run_verify(mut args: impl Iterator<Item = String>) -> i32 {
+    let mut report_path = None;
+    let mut dir = PathBuf::from(".");
+    let mut output = PathBuf::from(VERIFY_RESULT);
+    let mut max_depth = None;
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "--dir" | "--max-depth" | "--output" => args.next(),
+            "--json-errors" => continue, // picked up by main
+            _ if report_path.is_none() && !arg.starts_with("--") => {
+                report_path = Some(PathBuf::from(arg));
+                continue;
+            }
+            _ => {
+                let message = format!("unknown argument: {arg}");
+                report_error(&message, "usage", None, &message);
+                return 2;
+            }
+        };
+        let Some(value) = value else {
+            let needed = match arg.as_str() {
+                "--dir" => "folder",
+                "--max-depth" => "number",
+                _ => "file name",
+            };
+            let message = format!("{arg} needs a {needed}");
+            report_error(&message, "usage", None, &message);
+            return 2;
+        };
+        match arg.as_str() {
+            "--dir" => dir = PathBuf::from(value),
+            "--max-depth" => match value.parse() {
+                Ok(n) => max_depth = Some(n),
+                Err(_) => {
+                    let message = format!("invalid folder depth '{value}'");
+                    report_error(&message, "usage", None, &message);
+                    return 2;
+                }
+            },
+            _ => output = PathBuf::from(value),
+        }
+    }
+    let Some(report_path) = report_path else {
+        let message = "verify needs the report to check, e.g. exif_etl verify exif_output.csv --dir photos/";
+        report_error(message, "usage", None, message);
+        return 2;
+    };
+    let checked = Report::read(&report_path)
+        .map_err(|e| e.to_string())
+        .and_then(|report| verify::verify(&report, &dir, max_depth));
+    let result = match checked {
+        Ok(result) => result,
+        Err(e) => {
+            report_error(&format!("Can't verify {}: {}", report_path.display(), e), "input", Some(&report_path), &e);
+            return 2;
+        }
+    };
+    let write = || -> Result<(), Box<dyn Error>> {
+        let mut wtr = WriterBuilder::new().from_path(&output)?;
+        wtr.write_record(["path", "status", "detail"])?;
+        for found in &result.discrepancies {
+            wtr.write_record([&*found.path.to_string_lossy(), found.drift.as_str(), &found.detail])?;
+        }
+        wtr.flush()?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        report_error(&format!("Error writing {}: {}", output.display(), e), "output", Some(&output), &e.to_string());
+    }
+    status!(
+        "{} files listed in {}: {} missing, {} changed, {} new (details in {})",
+        result.listed,
+        report_path.display(),
+        result.count(Drift::Missing),
+        result.count(Drift::Changed),
+        result.count(Drift::New),
+        output.display()
+    );
+    if result.reparsed > 0 {
+        status!(
+            "{} files changed on disk since the report and were parsed again; {} still match it",
+            result.reparsed,
+            result.rewritten
+        );
+    }
+    if result.discrepancies.is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+/// Files to process, and how many discovery left out on purpose.
+struct Discovered {
+    files: Vec<PathBuf>,
+    unreadable: Vec<(PathBuf, io::Error)>, // sub-folders --recursive could not list, and why
+    symlinks_skipped: usize,               // symbolic links, left out without --follow-symlinks
+    duplicates: usize,                     // links to a file that was already found
+}
+
+fn main() {
+    // looked for up front, so even a bad command line is reported as JSON
+    JSON_ERRORS.store(env::args().any(|arg| arg == "--json-errors"), Ordering::Relaxed);
+    if env::args().nth(1).as_deref() == Some("verify") {
+        process::exit(run_verify(env::args().skip(2)));
+    }
+    let (opts, print) = parse_args().unwrap_or_else(|e| {
+        report_error(&e, "usage", None, &e);
+        process::exit(2);
+    });
+    JSON_ERRORS.store(opts.json_errors, Ordering::Relaxed);
+    match print {
+        Print::Config => {
+            print!("{}", opts.to_toml());
+            return;
+        }
+        Print::Schema => {
+            let schema = output_layout(&opts).schema();
+            println!("{}", serde_json::to_string_pretty(&schema).expect("the schema is plain JSON"));
+            return;
+        }
+        Print::Nothing => {}
+    }
+    if opts.compressed() {
+        // an unfinished gzip stream is unreadable, so Ctrl-C while writing lets the writers close it
+        let handler = ctrlc::set_handler(|| {
+            if WRITING.load(Ordering::SeqCst) {
+                INTERRUPTED.store(true, Ordering::SeqCst);
+            } else {
+                process::exit(130);
+            }
+        });
+        if let Err(e) = handler {
+            eprintln!("Warning: could not install the Ctrl-C handler: {}", e);
+        }
+    }
+    let threads = opts.worker_threads();
+    if threads > 0 {
+        if let Err(e) = rayon::ThreadPoolBuilder::new().num_threads(threads).build_global() {
+            eprintln!("Warning: could not set up {} threads: {}", threads, e);
+        }
+    }
+    DATA_ON_STDOUT.store(opts.stdout, Ordering::Relaxed);
+    let custom_columns = opts.extract_options().custom_columns();
+    let (_, unknown) = Field::parse_list(&opts.columns, &custom_columns);
+    if !unknown.is_empty() {
+        eprintln!(
+            "Warning: left out unknown columns {}; available: {}",
+            unknown.join(", "),
+            Field::available(&custom_columns)
+        );
+    }
+    let mut timings = Timings::new();
+
+    // Parallel EXIF extraction; each file's parse time is taken inside the
+    // closure (None for files skipped without parsing) and gathered afterwards
+    let extract_opts = opts.extract_options();
+    let extract_one = |file: &PathBuf| -> (Result<Row, ExtractError>, Option<Duration>) {
+        // a hand-made list can name anything; reading a FIFO could even block forever
+        if opts.files_from.is_some() {
+            let reason = match fs::metadata(file) {
+                Ok(meta) if meta.is_file() => None,
+                Ok(_) => Some(SKIPPED_NOT_A_FILE),
+                Err(_) => Some(SKIPPED_MISSING),
+            };
+            if let Some(reason) = reason {
+                return (Ok(Row::skipped(file.clone(), reason)), None);
+            }
+        }
+        if let Some(size) = oversized(file, opts.max_size) {
+            let mut row = Row::skipped(file.clone(), SKIPPED_TOO_LARGE);
+            row.file_size = Some(size);
+            return (Ok(row), None);
+        }
+        let parse_start = Instant::now();
+        let result = match extract_exif_with(file, &extract_opts) {
+            // --sniff also picks formats rexif has no parser for; those are
+            // reported as such instead of as broken files
+            Err(e) if opts.sniff && e.category == ErrorCategory::UnsupportedFormat => {
+                match sniff::sniff_file(file) {
+                    Ok(Some(kind)) if !kind.has_exif_reader() => {
+                        let mut row = Row::skipped(file.clone(), SKIPPED_NO_READER);
+                        row.detected_type = Some(kind);
+                        row.file_size = e.file_size;
+                        Ok(row)
+                    }
+                    _ => Err(e),
+                }
+            }
+            result => result,
+        };
+        (result, Some(parse_start.elapsed()))
+    };
+    let mut start = Instant::now();
+    // with a plain recursive scan the files are parsed as the walk lists them,
+    // so discovery and extraction are timed as one
+    let streaming = streams_discovery(&opts);
+    let (files, results, found, sample) = if streaming {
+        let (discovered, results) = extract_as_listed(&opts, extract_one);
+        report_discovered(&discovered);
+        let found = discovered.files.len();
+        (discovered.files, results, found, None)
+    } else {
+        let seed = opts.sample.map(|_| opts.seed.unwrap_or_else(random_seed));
+        let streamed = opts.sample.zip(seed).and_then(|(n, seed)| discover_sample(&opts, n, seed));
+        let (discovered, mut sample) = match streamed {
+            Some((discovered, population)) => (discovered, Some((population, seed.unwrap_or_default()))),
+            None => (discover_files(&opts), None),
+        };
+        if opts.count_only {
+            println!("{}", discovered.files.len());
+            return;
+        }
+        report_discovered(&discovered);
+        let mut files = discovered.files;
+        if opts.absolute_paths {
+            files = files.into_iter().map(absolute_path).collect();
+        }
+        files.sort(); // deterministic ordering
+        // when the listing couldn't be sampled as it came, the draw is from the
+        // sorted list; either way --order-by doesn't change which files are picked
+        if let (None, Some(n), Some(seed)) = (sample, opts.sample, seed) {
+            let (picked, population) = sample_files(std::mem::take(&mut files).into_iter(), n, seed);
+            files = picked;
+            sample = Some((population, seed));
+        }
+        processing_order(&mut files, opts.order_by, opts.order);
+        let found = files.len();
+        if let Some(limit) = opts.limit {
+            files.truncate(limit);
+        }
+        timings.phase_done("discovery", start);
+        start = Instant::now();
+
+        if opts.checkpoint.is_some() || opts.resume.is_some() {
+            let save_to = opts.checkpoint.as_deref().or(opts.resume.as_deref());
+            let session = run_checkpointed(&files, &opts, extract_one, &mut timings).unwrap_or_else(|e| {
+                report_error(&e.to_string(), "checkpoint", save_to, &e.to_string());
+                process::exit(1);
+            });
+            if session.failed_fast {
+                let read = session.done_before + session.rows.len() + session.failures.len();
+                let written = format!(
+                    "the rows read so far are in {}, and --resume {} goes on after the failed batch",
+                    session.output.display(),
+                    save_to.unwrap_or(Path::new("")).display()
+                );
+                stop_fast(session.failures, read, files.len(), &written, &opts);
+            }
+            timings.phase_done("extraction and writing", start);
+            // the tally covers this session, the files before the resume were counted then
+            print_summary(found - session.done_before, sample, &session.rows, &session.failures, &opts);
+            if let Some(command) = &opts.on_complete {
+                for line in run_on_complete(command, &[session.output]) {
+                    status!("{}", line);
+                }
+            }
+            report_timings(&timings, &opts);
+            return;
+        }
+        let results: Vec<(Result<Row, ExtractError>, Option<Duration>)> = if opts.fail_fast {
+            // every worker checks the flag before its next file, so the files still
+            // queued are dropped as soon as one fails; the ones in flight just finish
+            let failed = AtomicBool::new(false);
+            let all: Vec<Option<_>> = in_order(&files, |file| {
+                if failed.load(Ordering::Relaxed) {
+                    return None;
+                }
+                let (result, took) = extract_one(file);
+                if result.is_err() {
+                    failed.store(true, Ordering::Relaxed);
+                }
+                Some((result, took))
+            });
+            if failed.load(Ordering::Relaxed) {
+                let read = all.iter().flatten().count();
+                // workers racing each other may have hit more than one; the first in
+                // line is reported, and all of them are logged
+                let failures: Vec<ExtractError> = all.into_iter().flatten().filter_map(|(result, _)| result.err()).collect();
+                stop_fast(failures, read, files.len(), "no output was written", &opts);
+            }
+            all.into_iter().flatten().collect()
+        } else {
+            in_order(&files, extract_one)
+        };
+        (files, results, found, sample)
+    };
+    // the output is sorted by path, unless --order-by alone asked for another order
+    let mut ordered: Vec<_> = files.into_iter().zip(results).collect();
+    if !opts.parse_order_output() {
+        ordered.sort_by(|(a, _), (b, _)| a.cmp(b));
+    }
+    let (files, results): (Vec<PathBuf>, Vec<_>) = ordered.into_iter().unzip();
+    timings.add_files(
+        files
+            .iter()
+            .zip(&results)
+            .filter_map(|(file, (_, took))| Some((file.clone(), (*took)?))),
+    );
+    let (mut exif_rows, failures) = split_results(results, &opts);
+    // before --touch and --organize, so the drift is measured on the files as found
+    check_rows(&mut exif_rows, &opts);
+    timings.phase_done(if streaming { "discovery and extraction" } else { "extraction" }, start);
+
+    if opts.touch {
+        let start = Instant::now();
+        touch_files(&mut exif_rows, opts.dry_run);
+        timings.phase_done("touch", start);
+    }
+
+    // Optionally sort the images themselves into dated folders; this goes first
+    // so the output can record where each file went
+    if let Some(dest) = &opts.organize {
+        let start = Instant::now();
+        organize_files(&files, &mut exif_rows, &opts, dest);
+        timings.phase_done("organize", start);
+    }
 
     // Write results
-    if let Err(e) = to_csv(&exif_rows) {
-        eprintln!("Error writing CSV: {}", e);
+    let start = Instant::now();
+    let failed_rows: Vec<Row> = failures.iter().map(failure_row).collect();
+    let mut shown: Vec<&Row> = in_file_order(&files, &exif_rows, &failed_rows)
+        .into_iter()
+        .filter(|row| is_shown(row, &opts))
+        .collect();
+    sort_rows(&mut shown, opts.sort_by);
+    if opts.dedup_output {
+        let dropped = dedup_rows(&mut shown);
+        if dropped > 0 {
+            status!("Dropped {} rows for files that already had one (--dedup-output)", dropped);
+        }
+    }
+    #[cfg(feature = "tui")]
+    if opts.tui {
+        match browse(&shown) {
+            Ok(Some(picked)) => shown = picked,
+            Ok(None) => {
+                println!("Left the browser; no output was written");
+                return;
+            }
+            Err(e) => {
+                report_error(&format!("Error running the browser: {}", e), "tui", None, &e.to_string());
+                process::exit(1);
+            }
+        }
+    }
+    let outputs = write_output(&shown, &opts).unwrap_or_else(|e| {
+        report_error(&format!("Error writing output: {}", e), "output", None, &e.to_string());
+        Vec::new()
+    });
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        let message = "the output was closed properly but holds only the rows written before Ctrl-C";
+        report_error(&format!("Interrupted: {message}"), "interrupted", None, message);
+        process::exit(130);
+    }
+    let hooks = opts.on_complete.as_deref().map(|command| run_on_complete(command, &outputs)).unwrap_or_default();
+    if let Some(map) = &opts.path_map {
+        match write_path_map(map, &shown, opts.path_style()) {
+            Ok(()) => status!("Path map written to {} (keep it private)", map.display()),
+            Err(e) => report_error(&format!("Error writing path map {}: {}", map.display(), e), "output", Some(map), &e.to_string()),
+        }
+    }
+    let no_gps = shown.iter().filter(|row| row.gps.is_none()).count();
+    if opts.format == Format::Kml && no_gps > 0 {
+        status!("{} files without GPS were left out of the KML", no_gps);
+    }
+    if let Some(kml) = &opts.kml {
+        match write_kml(kml, &shown, &opts) {
+            Ok(placemarks) => status!(
+                "KML with {} placemarks written to {} ({} files without GPS left out)",
+                placemarks,
+                kml.display(),
+                no_gps
+            ),
+            Err(e) => report_error(&format!("Error writing KML {}: {}", kml.display(), e), "output", Some(kml), &e.to_string()),
+        }
+    }
+    if let Some(manifest) = &opts.manifest {
+        let entries = manifest_entries(&exif_rows, &failures, opts.path_style());
+        match write_manifest(manifest, &entries, &created_at(&opts)) {
+            Ok(()) => status!("Manifest of {} files written to {}", entries.len(), manifest.display()),
+            Err(e) => {
+                let prose = format!("Error writing manifest {}: {}", manifest.display(), e);
+                report_error(&prose, "output", Some(manifest), &e.to_string());
+            }
+        }
+    }
+    if let Some(file) = &opts.rollup {
+        let folders = rollup(&exif_rows, &failures, opts.rollup_depth.unwrap_or(0));
+        match write_rollup(file, &folders, opts.path_style(), &created_at(&opts)) {
+            Ok(()) => status!("Rollup of {} folders written to {}", folders.len(), file.display()),
+            Err(e) => report_error(&format!("Error writing rollup {}: {}", file.display(), e), "output", Some(file), &e.to_string()),
+        }
     }
+    timings.phase_done("writing", start);
+
+    if opts.preview > 0 {
+        print_preview(&shown[..shown.len().min(opts.preview)], opts.stdout);
+    }
+
+    print_summary(found, sample, &exif_rows, &failures, &opts);
+    for line in hooks {
+        status!("{}", line);
+    }
+    report_timings(&timings, &opts);
+
     //--- give users a moment to read the console output ---
-    println!("Sleeping 30 seconds so you can read the message …");
-    thread::sleep(Duration::from_secs(30));
+    // (not when piping: whoever reads stdout is a program, not a person; nor after --tui)
+    if !opts.stdout && !opts.tui {
+        println!("Sleeping 30 seconds so you can read the message …");
+        thread::sleep(Duration::from_secs(30));
+    }
 }
@@ -0,0 +1,190 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Local, NaiveDateTime};
+
+/// Folder (under the destination) for files without a usable capture date.
+const UNSORTED_DIR: &str = "unsorted";
+
+/// Default sub-folder template: `2023/08`.
+pub const DEFAULT_LAYOUT: &str = "%Y/%m";
+
+/// Parse an EXIF `DateTimeOriginal` string (`YYYY:MM:DD HH:MM:SS`).
+/// Cameras with an unset clock write all zeros or blanks, which yields `None`.
+pub fn parse_exif_datetime(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value.trim().trim_end_matches('\0'), "%Y:%m:%d %H:%M:%S").ok()
+}
+
+/// A file's modification time on the local clock, the stand-in for a missing EXIF date.
+pub fn file_mtime(path: &Path) -> Option<NaiveDateTime> {
+    let modified: SystemTime = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(DateTime::<Local>::from(modified).naive_local())
+}
+
+/// Seconds from `taken` (an EXIF date) to the file's modification time: positive
+/// when the file was written later, as after a re-save. `None` without an mtime.
+pub fn date_drift(taken: NaiveDateTime, path: &Path) -> Option<i64> {
+    Some((file_mtime(path)? - taken).num_seconds())
+}
+
+/// Settings shared by every file of one organize run.
+#[derive(Clone, Debug)]
+pub struct OrganizeOptions {
+    pub dest: PathBuf,
+    pub layout: String,   // strftime template for the dated sub-folders, e.g. "%Y/%m"
+    pub move_files: bool, // rename into place instead of copying
+    pub dry_run: bool,    // only work out the targets, touch nothing
+}
+
+/// Files images into dated folders, remembering the targets it handed out so
+/// that two files with the same name never get the same place, even in a dry run.
+pub struct Organizer {
+    opts: OrganizeOptions,
+    claimed: HashSet<PathBuf>,
+}
+
+impl Organizer {
+    pub fn new(opts: OrganizeOptions) -> Organizer {
+        Organizer {
+            opts,
+            claimed: HashSet::new(),
+        }
+    }
+
+    pub fn options(&self) -> &OrganizeOptions {
+        &self.opts
+    }
+
+    /// Sub-folder of the destination for `date_taken`, or `unsorted` when undated.
+    fn target_dir(&self, date_taken: Option<NaiveDateTime>) -> PathBuf {
+        match date_taken {
+            Some(dt) => self.opts.dest.join(dt.format(&self.opts.layout).to_string()),
+            None => self.opts.dest.join(UNSORTED_DIR),
+        }
+    }
+
+    /// First path in `dir` named after `file_name` that neither exists nor was
+    /// handed out already: `name.jpg`, then `name_1.jpg`, `name_2.jpg`, …
+    fn free_target(&self, dir: &Path, file_name: &Path) -> PathBuf {
+        let is_free = |p: &PathBuf| !p.exists() && !self.claimed.contains(p);
+        let candidate = dir.join(file_name);
+        if is_free(&candidate) {
+            return candidate;
+        }
+        let stem = file_name.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = file_name.extension().map(|e| e.to_string_lossy());
+        (1..)
+            .map(|n| match &ext {
+                Some(ext) => dir.join(format!("{stem}_{n}.{ext}")),
+                None => dir.join(format!("{stem}_{n}")),
+            })
+            .find(is_free)
+            .expect("unbounded suffix search always finds a free name")
+    }
+
+    /// Copy (or move) `src` into its dated folder; in a dry run only pick the target.
+    /// Returns the path the file ended up at; existing files are never overwritten.
+    pub fn organize_file(&mut self, src: &Path, date_taken: Option<NaiveDateTime>) -> io::Result<PathBuf> {
+        let dir = self.target_dir(date_taken);
+        let file_name = src
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+        if !self.opts.dry_run {
+            fs::create_dir_all(&dir)?;
+        }
+        loop {
+            let target = self.free_target(&dir, Path::new(file_name));
+            // the check above can't see a file that turns up right after it, so
+            // the write itself refuses to replace one, and the next name is tried
+            let placed = match (self.opts.dry_run, self.opts.move_files) {
+                (true, _) => Ok(()),
+                (false, true) => move_new(src, &target),
+                (false, false) => copy_new(src, &target),
+            };
+            self.claimed.insert(target.clone());
+            match placed {
+                Ok(()) => return Ok(target),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Copy `src` to `dst`, which must not exist yet (`AlreadyExists` if it does).
+/// Permissions are copied as [`fs::copy`] does; a half-written copy is removed.
+fn copy_new(src: &Path, dst: &Path) -> io::Result<()> {
+    let mut input = fs::File::open(src)?;
+    let mut output = fs::File::options().write(true).create_new(true).open(dst)?;
+    let copied = io::copy(&mut input, &mut output).and_then(|_| output.set_permissions(input.metadata()?.permissions()));
+    if copied.is_err() {
+        drop(output);
+        let _ = fs::remove_file(dst);
+    }
+    copied
+}
+
+/// Move `src` to `dst` without replacing a file already there (`AlreadyExists`):
+/// a hard link then removing `src`, since a plain rename would overwrite. Falls
+/// back to copy + delete where links can't be made, e.g. across filesystems.
+fn move_new(src: &Path, dst: &Path) -> io::Result<()> {
+    match fs::hard_link(src, dst) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(e),
+        Err(_) => copy_new(src, dst)?,
+    }
+    fs::remove_file(src).inspect_err(|_| {
+        // leave the file where it was rather than in two places
+        let _ = fs::remove_file(dst);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io};
+
+    use chrono::NaiveDate;
+
+    use super::{copy_new, move_new, OrganizeOptions, Organizer};
+    use crate::testutil::ScratchDir;
+
+    #[test]
+    fn copy_and_move_never_replace_a_file() {
+        let dir = ScratchDir::new("organize-no-clobber");
+        let src = dir.file("a.jpg", b"new");
+        let taken = dir.file("taken.jpg", b"old");
+        for place in [copy_new, move_new] {
+            let err = place(&src, &taken).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+            assert_eq!(fs::read(&taken).unwrap(), b"old");
+            assert_eq!(fs::read(&src).unwrap(), b"new", "the source stays put");
+        }
+        move_new(&src, &dir.path().join("moved.jpg")).unwrap();
+        assert!(!src.exists());
+        assert_eq!(fs::read(dir.path().join("moved.jpg")).unwrap(), b"new");
+    }
+
+    #[test]
+    fn clashing_names_get_a_suffix() {
+        let dir = ScratchDir::new("organize-suffix");
+        let dest = dir.path().join("sorted");
+        fs::create_dir_all(dest.join("2023/08")).unwrap();
+        fs::write(dest.join("2023/08/a.jpg"), b"already there").unwrap();
+        let src = dir.file("a.jpg", b"photo");
+        let mut organizer = Organizer::new(OrganizeOptions {
+            dest: dest.clone(),
+            layout: "%Y/%m".to_string(),
+            move_files: false,
+            dry_run: false,
+        });
+        let date = NaiveDate::from_ymd_opt(2023, 8, 1).unwrap().and_hms_opt(12, 0, 0);
+        assert_eq!(organizer.organize_file(&src, date).unwrap(), dest.join("2023/08/a_1.jpg"));
+        assert_eq!(organizer.organize_file(&src, date).unwrap(), dest.join("2023/08/a_2.jpg"));
+        assert_eq!(fs::read(dest.join("2023/08/a.jpg")).unwrap(), b"already there");
+        assert_eq!(fs::read(dest.join("2023/08/a_2.jpg")).unwrap(), b"photo");
+    }
+}
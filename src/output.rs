@@ -0,0 +1,1031 @@
+//! Writers that turn [`Row`]s into output files.
+
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDate;
+use csv::{Writer, WriterBuilder};
+use flate2::{write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+#[cfg(feature = "parquet")]
+pub use crate::parquet_output::ParquetOutput;
+use crate::{
+    row::{Column, Row, ALL_COLUMNS, LONG_HEADER, LONG_RAW_HEADER, SCHEMA_VERSION, TAGS_HEADER},
+    tags::{self, Tag},
+};
+
+/// Output file format (`--format`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Csv,
+    Tsv,     // the CSV layout, tab-separated
+    Jsonl,   // one JSON object per image and line
+    Kml,     // one Google Earth placemark per geotagged photo
+    Html,    // self-contained report with a sortable, filterable table
+    Md,      // GitHub-flavoured Markdown table
+    Xml,     // one <image> element per image, its tags as <tag name="…"> children
+    Parquet, // typed columns for data frames (needs the `parquet` feature)
+}
+
+impl Format {
+    /// Whether line breaks inside a value survive in this format (Markdown turns
+    /// them into `<br>`, JSON escapes them, XML text and Parquet strings keep them as they are).
+    pub fn keeps_newlines(self) -> bool {
+        matches!(self, Format::Md | Format::Jsonl | Format::Xml | Format::Parquet)
+    }
+
+    /// File extension, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+            Format::Jsonl => "jsonl",
+            Format::Kml => "kml",
+            Format::Html => "html",
+            Format::Md => "md",
+            Format::Xml => "xml",
+            Format::Parquet => "parquet",
+        }
+    }
+}
+
+/// How file paths appear in the output (`--hash-paths`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathStyle {
+    #[default]
+    Full,
+    HashAndName, // `<hash>/<file name>`: the folders are hidden, the file name kept
+    Hash,        // only the hash
+}
+
+impl PathStyle {
+    /// `path` as it should be written.
+    pub fn show(self, path: &Path) -> String {
+        match self {
+            PathStyle::Full => path.to_string_lossy().into_owned(),
+            PathStyle::HashAndName => {
+                let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+                format!("{}/{}", path_hash(path), name)
+            }
+            PathStyle::Hash => path_hash(path),
+        }
+    }
+}
+
+/// Stable 16-hex-digit hash of a path (64-bit FNV-1a): the same path gives the
+/// same hash on every run and machine, so anonymized outputs can still be joined.
+pub fn path_hash(path: &Path) -> String {
+    let hash = path
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+    format!("{hash:016x}")
+}
+
+/// Byte-order mark that tells Excel a CSV is UTF-8 rather than the system code page.
+pub const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// One output column picked with `--columns`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Field {
+    Fixed(Column),
+    Custom(String), // a ColumnExtractor's name
+    Tag(String),    // the first tag matching this name (see `Tag::matches`), headed as typed
+}
+
+impl Field {
+    /// Resolve `--columns` names: fixed column names win, then the `custom`
+    /// columns, then tag names. Names that are none of these come back second.
+    pub fn parse_list<'a>(names: &'a [String], custom: &[String]) -> (Vec<Field>, Vec<&'a str>) {
+        let mut fields = Vec::with_capacity(names.len());
+        let mut unknown = Vec::new();
+        for name in names {
+            if let Some(column) = Column::from_name(name) {
+                fields.push(Field::Fixed(column));
+            } else if custom.contains(name) {
+                fields.push(Field::Custom(name.clone()));
+            } else if tags::is_known_tag(name) {
+                fields.push(Field::Tag(name.clone()));
+            } else {
+                unknown.push(name.as_str());
+            }
+        }
+        (fields, unknown)
+    }
+
+    /// The names `--columns` accepts, for messages about the ones it doesn't.
+    pub fn available(custom: &[String]) -> String {
+        let mut available: Vec<&str> = ALL_COLUMNS.iter().map(|c| c.name()).collect();
+        available.extend(custom.iter().map(String::as_str));
+        format!(
+            "{}, or any EXIF tag name (Model, DateTimeOriginal, GPSLatitude, 0x013b, …)",
+            available.join(", ")
+        )
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Field::Fixed(column) => column.name(),
+            Field::Custom(name) | Field::Tag(name) => name,
+        }
+    }
+}
+
+/// What every output file of a run looks like.
+#[derive(Clone)]
+pub struct Layout {
+    pub format: Format,
+    pub columns: Vec<Column>,        // fixed columns before the tag cells
+    pub long: bool,                  // one path,tag,value line per tag instead
+    pub created_at: String,          // stamp for the leading `# csv_created_at` line
+    pub scan_dir: String,            // directory that was scanned, for report headers
+    pub max_cell_width: usize,       // Markdown cells longer than this are cut with "…" (0 = no limit)
+    pub custom_columns: Vec<String>, // ColumnExtractor names, after the fixed columns
+    pub path_style: PathStyle,       // full paths, or hashed for sharing
+    pub bom: bool,                   // start CSV/TSV files with a UTF-8 byte-order mark (for Excel)
+    pub compress: bool,              // gzip every output file
+    pub fields: Option<Vec<Field>>,  // --columns: exactly these, replacing all of the above and the tag cells
+    pub other_tags: bool,            // --other-tags: with `fields`, the tags it doesn't pick still follow
+    pub raw_tags: bool,              // --raw-tags: each tag's ID, data type and unconverted value too
+}
+
+impl Layout {
+    /// Header names of the fixed and custom columns (the tag cells not included).
+    pub fn column_names(&self) -> Vec<&str> {
+        if let Some(fields) = &self.fields {
+            return fields.iter().map(Field::name).collect();
+        }
+        let fixed = self.columns.iter().map(|c| c.name());
+        fixed.chain(self.custom_columns.iter().map(String::as_str)).collect()
+    }
+
+    /// One row's cells for [`Layout::column_names`]; custom columns and tags a row lacks stay blank.
+    pub fn cells(&self, row: &Row) -> Vec<String> {
+        if let Some(fields) = &self.fields {
+            return fields
+                .iter()
+                .map(|field| match field {
+                    Field::Fixed(column) => self.fixed_cell(row, *column),
+                    Field::Custom(name) => row.custom_value(name).unwrap_or_default().to_string(),
+                    Field::Tag(name) => row.tag_value(name).unwrap_or_default().to_string(),
+                })
+                .collect();
+        }
+        let fixed = self.columns.iter().map(|&c| self.fixed_cell(row, c));
+        let custom = self
+            .custom_columns
+            .iter()
+            .map(|name| row.custom_value(name).unwrap_or_default().to_string());
+        fixed.chain(custom).collect()
+    }
+
+    /// Whether each row ends with its "tag: value" cells (not when `--columns`
+    /// picked the columns, unless `--other-tags` asks for the rest too).
+    pub fn tag_cells(&self) -> bool {
+        self.fields.is_none() || self.other_tags
+    }
+
+    /// The tags that go into a row's tag cells: all of them, or with `--other-tags`
+    /// those no `--columns` name picked.
+    pub fn tags<'a>(&'a self, row: &'a Row) -> impl Iterator<Item = &'a Tag> + 'a {
+        let picked: Vec<&str> = self
+            .fields
+            .iter()
+            .flatten()
+            .filter_map(|field| match field {
+                Field::Tag(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        row.tags
+            .iter()
+            .filter(move |tag| !picked.iter().any(|name| tag.matches(name)))
+    }
+
+    /// A "tag: value" cell, with `[0x829a rational 1/250]` after it under `--raw-tags`.
+    pub fn tag_cell(&self, tag: &Tag) -> String {
+        if self.raw_tags {
+            format!("{}: {} [{} {} {}]", tag.label, tag.value, tag.hex_id(), tag.kind, tag.raw)
+        } else {
+            format!("{}: {}", tag.label, tag.value)
+        }
+    }
+
+    /// `--print-schema`: every column this layout writes, in order, with its type
+    /// and meaning, under the [`SCHEMA_VERSION`] it belongs to. All writers take
+    /// their columns from [`Layout::column_names`], so this holds for every format.
+    pub fn schema(&self) -> Value {
+        let column = |name: &str, kind: &str, description: &str| {
+            json!({ "name": name, "type": kind, "description": description })
+        };
+        let mut columns = Vec::new();
+        if self.long {
+            columns.push(column(LONG_HEADER[0], "string", Column::Path.schema().1));
+            columns.push(column(LONG_HEADER[1], "string", "tag label, e.g. \"Date of original image\""));
+            columns.push(column(LONG_HEADER[2], "string", "tag value, readable"));
+            if self.raw_tags {
+                columns.push(column(LONG_RAW_HEADER[0], "string", "tag ID in hex, e.g. 0x829a"));
+                columns.push(column(LONG_RAW_HEADER[1], "string", "EXIF data type as stored, e.g. rational"));
+                columns.push(column(LONG_RAW_HEADER[2], "string", "unconverted value"));
+            }
+        } else {
+            let types = self.column_types();
+            for (name, (kind, description)) in self.column_names().into_iter().zip(types) {
+                columns.push(column(name, kind, &description));
+            }
+            if self.tag_cells() {
+                let description = match self.format {
+                    Format::Jsonl => "object of tag name to value",
+                    Format::Parquet => "JSON object of tag name to value",
+                    Format::Xml => "one <tag name=\"…\"> element per tag",
+                    _ => "the remaining cells, one \"label: value\" per tag",
+                };
+                columns.push(column(TAGS_HEADER, "tags", description));
+            }
+        }
+        json!({
+            "schema_version": SCHEMA_VERSION,
+            "format": self.format.extension(),
+            "columns": columns,
+        })
+    }
+
+    /// Type and description of each of [`Layout::column_names`].
+    pub(crate) fn column_types(&self) -> Vec<(&'static str, String)> {
+        let fixed = |c: &Column| {
+            let (kind, description) = c.schema();
+            (kind, description.to_string())
+        };
+        let custom = || ("string", "custom column (a ColumnExtractor)".to_string());
+        match &self.fields {
+            Some(fields) => fields
+                .iter()
+                .map(|field| match field {
+                    Field::Fixed(c) => fixed(c),
+                    Field::Custom(_) => custom(),
+                    Field::Tag(name) => ("string", format!("value of the EXIF tag {name}")),
+                })
+                .collect(),
+            None => self
+                .columns
+                .iter()
+                .map(fixed)
+                .chain(self.custom_columns.iter().map(|_| custom()))
+                .collect(),
+        }
+    }
+
+    fn fixed_cell(&self, row: &Row, column: Column) -> String {
+        match column {
+            Column::Path => self.path_style.show(&row.path),
+            _ => row.cell(column),
+        }
+    }
+}
+
+/// Where an output file goes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Destination {
+    File(PathBuf),
+    Append(PathBuf), // the end of an existing CSV/TSV/JSONL file; no preamble or header (--resume)
+    Stdout,
+}
+
+impl Destination {
+    fn open(&self, compress: bool) -> io::Result<Sink> {
+        let out: Box<dyn Write> = match self {
+            Destination::File(path) => Box::new(BufWriter::new(File::create(path)?)),
+            Destination::Append(path) => Box::new(BufWriter::new(File::options().append(true).open(path)?)),
+            Destination::Stdout => Box::new(BufWriter::new(io::stdout())),
+        };
+        Ok(if compress {
+            Sink::Gzip(GzEncoder::new(out, Compression::default()))
+        } else {
+            Sink::Plain(out)
+        })
+    }
+}
+
+/// An open output stream, gzip-compressed or not. [`Sink::finish`] must be
+/// called to end the gzip stream; flushing alone leaves it truncated.
+pub enum Sink {
+    Plain(Box<dyn Write>),
+    Gzip(GzEncoder<Box<dyn Write>>),
+}
+
+impl Sink {
+    /// Write the gzip trailer, if any, and flush.
+    pub fn finish(self) -> io::Result<()> {
+        let mut out = match self {
+            Sink::Plain(out) => out,
+            Sink::Gzip(gz) => gz.finish()?,
+        };
+        out.flush()
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Plain(out) => out.write(buf),
+            Sink::Gzip(gz) => gz.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Plain(out) => out.flush(),
+            Sink::Gzip(gz) => gz.flush(),
+        }
+    }
+}
+
+impl fmt::Display for Destination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Destination::File(path) | Destination::Append(path) => write!(f, "{}", path.display()),
+            Destination::Stdout => f.write_str("standard output"),
+        }
+    }
+}
+
+/// A file being written row by row, in any format.
+pub trait RowWriter {
+    /// Append one image's data (formats may leave some rows out, e.g. KML without GPS).
+    fn write_row(&mut self, row: &Row) -> Result<(), Box<dyn Error>>;
+
+    /// Write any trailer and flush, returning where the output went and how many rows it holds.
+    fn finish(self: Box<Self>) -> Result<(Destination, usize), Box<dyn Error>>;
+}
+
+/// Open `dest` in the layout's format, with its preamble already written.
+pub fn create_writer(dest: &Destination, layout: &Layout) -> Result<Box<dyn RowWriter>, Box<dyn Error>> {
+    Ok(match layout.format {
+        Format::Csv | Format::Tsv => Box::new(CsvOutput::create(dest, layout)?),
+        Format::Jsonl => Box::new(JsonlOutput::create(dest, layout)?),
+        Format::Kml => Box::new(KmlOutput::create(dest, layout)?),
+        Format::Html => Box::new(HtmlOutput::create(dest, layout)?),
+        Format::Md => Box::new(MdOutput::create(dest, layout)?),
+        Format::Xml => Box::new(XmlOutput::create(dest, layout)?),
+        #[cfg(feature = "parquet")]
+        Format::Parquet => Box::new(ParquetOutput::create(dest, layout)?),
+        #[cfg(not(feature = "parquet"))]
+        Format::Parquet => return Err("--format parquet needs a build with the parquet feature".into()),
+    })
+}
+
+/// One CSV (or TSV) file being written row by row.
+pub struct CsvOutput {
+    wtr: Writer<Sink>,
+    dest: Destination,
+    layout: Layout,
+    rows: usize,
+}
+
+impl CsvOutput {
+    /// Open `dest` and write the timestamp line and the header row (not when
+    /// appending: the file has them already).
+    pub fn create(dest: &Destination, layout: &Layout) -> Result<CsvOutput, Box<dyn Error>> {
+        let mut out = dest.open(layout.compress)?;
+        let appending = matches!(dest, Destination::Append(_));
+        if layout.bom && !appending {
+            out.write_all(UTF8_BOM)?;
+        }
+        let delimiter = if layout.format == Format::Tsv { b'\t' } else { b',' };
+        let mut wtr = WriterBuilder::new().flexible(true).delimiter(delimiter).from_writer(out);
+
+        // Comment‑style timestamp row (many CSV readers ignore lines that start with '#')
+        if !appending {
+            wtr.write_record(&[format!("# csv_created_at: {}; schema_version: {}", layout.created_at, SCHEMA_VERSION)])?;
+            if layout.long && layout.raw_tags {
+                wtr.write_record(LONG_HEADER.iter().chain(&LONG_RAW_HEADER))?;
+            } else if layout.long {
+                wtr.write_record(LONG_HEADER)?;
+            } else {
+                let mut names = layout.column_names();
+                if layout.tag_cells() {
+                    names.push(TAGS_HEADER);
+                }
+                wtr.write_record(names)?;
+            }
+        }
+        Ok(CsvOutput {
+            wtr,
+            dest: dest.clone(),
+            layout: layout.clone(),
+            rows: 0,
+        })
+    }
+}
+
+impl RowWriter for CsvOutput {
+    fn write_row(&mut self, row: &Row) -> Result<(), Box<dyn Error>> {
+        if self.layout.long {
+            for (mut record, tag) in row.to_long_records().zip(&row.tags) {
+                record[0] = self.layout.path_style.show(&row.path);
+                if self.layout.raw_tags {
+                    let raw = [tag.hex_id(), tag.kind.to_string(), tag.raw.clone()];
+                    self.wtr.write_record(record.iter().chain(&raw))?;
+                } else {
+                    self.wtr.write_record(&record)?;
+                }
+            }
+        } else {
+            let mut record = self.layout.cells(row);
+            if self.layout.tag_cells() {
+                record.extend(self.layout.tags(row).map(|tag| self.layout.tag_cell(tag)));
+            }
+            self.wtr.write_record(record)?;
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(Destination, usize), Box<dyn Error>> {
+        self.wtr.into_inner().map_err(|e| e.into_error())?.finish()?;
+        Ok((self.dest, self.rows))
+    }
+}
+
+/// JSON Lines: one object per image, the columns as keys (blank cells as `null`)
+/// and, unless `--columns` picked them, a `tags` object keyed by tag name.
+pub struct JsonlOutput {
+    out: Sink,
+    dest: Destination,
+    layout: Layout,
+    rows: usize,
+}
+
+impl JsonlOutput {
+    pub fn create(dest: &Destination, layout: &Layout) -> Result<JsonlOutput, Box<dyn Error>> {
+        Ok(JsonlOutput {
+            out: dest.open(layout.compress)?,
+            dest: dest.clone(),
+            layout: layout.clone(),
+            rows: 0,
+        })
+    }
+}
+
+impl RowWriter for JsonlOutput {
+    fn write_row(&mut self, row: &Row) -> Result<(), Box<dyn Error>> {
+        let mut record = Map::new();
+        for (name, cell) in self.layout.column_names().into_iter().zip(self.layout.cells(row)) {
+            let value = if cell.is_empty() { Value::Null } else { Value::String(cell) };
+            record.insert(name.to_string(), value);
+        }
+        if self.layout.tag_cells() {
+            let tags = self
+                .layout
+                .tags(row)
+                .map(|tag| {
+                    let value = Value::String(tag.value.clone());
+                    if !self.layout.raw_tags {
+                        return (tag.name.clone(), value);
+                    }
+                    let mut fields = Map::new();
+                    fields.insert("value".to_string(), value);
+                    fields.insert("id".to_string(), Value::String(tag.hex_id()));
+                    fields.insert("type".to_string(), Value::String(tag.kind.to_string()));
+                    fields.insert("raw".to_string(), Value::String(tag.raw.clone()));
+                    (tag.name.clone(), Value::Object(fields))
+                })
+                .collect();
+            record.insert(TAGS_HEADER.to_string(), Value::Object(tags));
+        }
+        serde_json::to_writer(&mut self.out, &record)?;
+        self.out.write_all(b"\n")?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(Destination, usize), Box<dyn Error>> {
+        self.out.finish()?;
+        Ok((self.dest, self.rows))
+    }
+}
+
+/// A KML document with one `<Placemark>` per geotagged photo, in one `<Folder>`
+/// per capture day (files without a date last, in "undated"); others are left out.
+/// Placemarks are collected until `finish` so each day's can be written together.
+pub struct KmlOutput {
+    out: Sink,
+    dest: Destination,
+    path_style: PathStyle,
+    days: BTreeMap<Option<NaiveDate>, String>, // placemarks by capture day; None sorts first, so it is moved last
+    rows: usize,
+}
+
+impl KmlOutput {
+    /// Open `dest` and write the `<kml><Document>` wrapper.
+    pub fn create(dest: &Destination, layout: &Layout) -> Result<KmlOutput, Box<dyn Error>> {
+        let mut out = dest.open(layout.compress)?;
+        let name = match dest {
+            Destination::File(path) | Destination::Append(path) => {
+                path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+            }
+            Destination::Stdout => "exif_output".to_string(),
+        };
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(out, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+        writeln!(out, "<Document>")?;
+        writeln!(out, "  <name>{}</name>", xml_escape(&name))?;
+        writeln!(out, "  <description>created {}</description>", xml_escape(&layout.created_at))?;
+        Ok(KmlOutput {
+            out,
+            dest: dest.clone(),
+            path_style: layout.path_style,
+            days: BTreeMap::new(),
+            rows: 0,
+        })
+    }
+}
+
+impl RowWriter for KmlOutput {
+    fn write_row(&mut self, row: &Row) -> Result<(), Box<dyn Error>> {
+        let Some((lat, lon)) = row.gps else {
+            return Ok(());
+        };
+        let name = match self.path_style {
+            PathStyle::Hash => path_hash(&row.path),
+            _ => row.path.file_name().unwrap_or(row.path.as_os_str()).to_string_lossy().into_owned(),
+        };
+        let taken = row.date_taken.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string());
+        let camera = row.model.as_ref().map(|model| format!("with {model}"));
+        let description: Vec<String> = taken.into_iter().chain(camera).collect();
+        let placemarks = self.days.entry(row.date_taken.map(|t| t.date())).or_default();
+        placemarks.push_str("    <Placemark>\n");
+        placemarks.push_str(&format!("      <name>{}</name>\n", xml_escape(&name)));
+        if !description.is_empty() {
+            placemarks.push_str(&format!("      <description>{}</description>\n", xml_escape(&format!("Taken {}", description.join(" ")))));
+        }
+        // KML wants longitude first; 7 decimals is about a centimetre
+        placemarks.push_str(&format!("      <Point><coordinates>{lon:.7},{lat:.7}</coordinates></Point>\n"));
+        placemarks.push_str("    </Placemark>\n");
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(Destination, usize), Box<dyn Error>> {
+        let undated = self.days.remove(&None).map(|placemarks| ("undated".to_string(), placemarks));
+        let dated = std::mem::take(&mut self.days)
+            .into_iter()
+            .filter_map(|(day, placemarks)| Some((day?.to_string(), placemarks)));
+        for (name, placemarks) in dated.chain(undated) {
+            writeln!(self.out, "  <Folder>")?;
+            writeln!(self.out, "    <name>{}</name>", name)?;
+            self.out.write_all(placemarks.as_bytes())?;
+            writeln!(self.out, "  </Folder>")?;
+        }
+        writeln!(self.out, "</Document>")?;
+        writeln!(self.out, "</kml>")?;
+        self.out.finish()?;
+        Ok((self.dest, self.rows))
+    }
+}
+
+/// An `<images>` document: one `<image>` per row, holding a `<column>` per
+/// non-blank cell and, unless `--columns` picked them, a `<tag>` per tag.
+pub struct XmlOutput {
+    out: Sink,
+    dest: Destination,
+    layout: Layout,
+    rows: usize,
+}
+
+impl XmlOutput {
+    /// Open `dest` and write the declaration and the opening `<images>` tag.
+    pub fn create(dest: &Destination, layout: &Layout) -> Result<XmlOutput, Box<dyn Error>> {
+        let mut out = dest.open(layout.compress)?;
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<images created="{}" schema_version="{}">"#,
+            xml_escape(&layout.created_at),
+            SCHEMA_VERSION
+        )?;
+        Ok(XmlOutput {
+            out,
+            dest: dest.clone(),
+            layout: layout.clone(),
+            rows: 0,
+        })
+    }
+}
+
+impl RowWriter for XmlOutput {
+    fn write_row(&mut self, row: &Row) -> Result<(), Box<dyn Error>> {
+        writeln!(self.out, "  <image>")?;
+        for (name, cell) in self.layout.column_names().into_iter().zip(self.layout.cells(row)) {
+            if !cell.is_empty() {
+                writeln!(self.out, r#"    <column name="{}">{}</column>"#, xml_escape(name), xml_escape(&cell))?;
+            }
+        }
+        if self.layout.tag_cells() {
+            for tag in self.layout.tags(row) {
+                let (name, value) = (xml_escape(&tag.name), xml_escape(&tag.value));
+                if self.layout.raw_tags {
+                    let (kind, raw) = (tag.kind, xml_escape(&tag.raw));
+                    let id = tag.hex_id();
+                    writeln!(self.out, r#"    <tag name="{name}" id="{id}" type="{kind}" raw="{raw}">{value}</tag>"#)?;
+                } else {
+                    writeln!(self.out, r#"    <tag name="{name}">{value}</tag>"#)?;
+                }
+            }
+        }
+        writeln!(self.out, "  </image>")?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(Destination, usize), Box<dyn Error>> {
+        writeln!(self.out, "</images>")?;
+        self.out.finish()?;
+        Ok((self.dest, self.rows))
+    }
+}
+
+/// Rows beyond this are left out of an HTML report so browsers can still open it.
+pub const HTML_ROW_CAP: usize = 20_000;
+
+/// Sorting (click a header) and filtering (type in the box) for the HTML report.
+const HTML_SCRIPT: &str = r#"
+const table = document.getElementById('rows');
+const body = table.tBodies[0];
+document.getElementById('filter').addEventListener('input', e => {
+  const needle = e.target.value.toLowerCase();
+  for (const tr of body.rows) tr.hidden = needle !== '' && !tr.textContent.toLowerCase().includes(needle);
+});
+table.tHead.addEventListener('click', e => {
+  const th = e.target.closest('th');
+  if (!th || th.classList.contains('tags')) return;
+  const col = th.cellIndex, asc = th.dataset.dir !== 'asc';
+  for (const other of table.tHead.rows[0].cells) delete other.dataset.dir;
+  th.dataset.dir = asc ? 'asc' : 'desc';
+  const key = tr => tr.cells[col].textContent;
+  const collator = new Intl.Collator(undefined, { numeric: true });
+  const sorted = [...body.rows].sort((a, b) => {
+    const x = key(a), y = key(b), nx = parseFloat(x), ny = parseFloat(y);
+    const cmp = (x === '') - (y === '') || (!isNaN(nx) && !isNaN(ny) ? nx - ny : collator.compare(x, y));
+    return asc ? cmp : -cmp;
+  });
+  body.append(...sorted);
+});
+"#;
+
+const HTML_STYLE: &str = "body{font-family:sans-serif;margin:1em}table{border-collapse:collapse}\
+th,td{border:1px solid #ccc;padding:2px 6px;vertical-align:top;font-size:13px}\
+th{background:#eee;cursor:pointer;position:sticky;top:0}th[data-dir=asc]::after{content:' \\25B2'}\
+th[data-dir=desc]::after{content:' \\25BC'}details td{border:none}";
+
+/// A single-file HTML report: scan summary, then one table row per image.
+/// Rows are collected until `finish` so the header can show the final counts.
+pub struct HtmlOutput {
+    out: Sink,
+    dest: Destination,
+    layout: Layout,
+    body: String,
+    rows: usize,
+    skipped: usize, // rows with an error set
+    dropped: usize, // rows over HTML_ROW_CAP
+}
+
+impl HtmlOutput {
+    pub fn create(dest: &Destination, layout: &Layout) -> Result<HtmlOutput, Box<dyn Error>> {
+        Ok(HtmlOutput {
+            out: dest.open(layout.compress)?,
+            dest: dest.clone(),
+            layout: layout.clone(),
+            body: String::new(),
+            rows: 0,
+            skipped: 0,
+            dropped: 0,
+        })
+    }
+}
+
+impl RowWriter for HtmlOutput {
+    fn write_row(&mut self, row: &Row) -> Result<(), Box<dyn Error>> {
+        if row.error.is_some() {
+            self.skipped += 1;
+        }
+        if self.rows >= HTML_ROW_CAP {
+            self.dropped += 1;
+            return Ok(());
+        }
+        self.body.push_str("<tr>");
+        for cell in self.layout.cells(row) {
+            self.body.push_str(&format!("<td>{}</td>", xml_escape(&cell)));
+        }
+        // The full tag list is long, so it folds away behind a count
+        let tags: Vec<&Tag> = self.layout.tags(row).collect();
+        if !self.layout.tag_cells() {
+            self.body.push_str("</tr>\n");
+        } else if tags.is_empty() {
+            self.body.push_str("<td></td></tr>\n");
+        } else {
+            self.body.push_str(&format!("<td><details><summary>{} tags</summary><table>", tags.len()));
+            for tag in tags {
+                let (label, value) = (xml_escape(&tag.label), xml_escape(&tag.value));
+                self.body.push_str(&format!("<tr><td>{}</td><td>{}</td>", label, value));
+                if self.layout.raw_tags {
+                    let raw = xml_escape(&tag.raw);
+                    self.body.push_str(&format!("<td>{}</td><td>{}</td><td>{}</td>", tag.hex_id(), tag.kind, raw));
+                }
+                self.body.push_str("</tr>");
+            }
+            self.body.push_str("</table></details></td></tr>\n");
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(Destination, usize), Box<dyn Error>> {
+        let HtmlOutput { mut out, dest, layout, body, rows, skipped, dropped } = *self;
+        let title = format!("EXIF report for {}", layout.scan_dir);
+        writeln!(out, "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">")?;
+        writeln!(out, "<title>{}</title><style>{}</style></head><body>", xml_escape(&title), HTML_STYLE)?;
+        writeln!(out, "<h1>{}</h1>", xml_escape(&title))?;
+        writeln!(
+            out,
+            "<p>Created {} (schema version {}). {} images, {} of them not parsed.</p>",
+            xml_escape(&layout.created_at),
+            SCHEMA_VERSION,
+            rows + dropped,
+            skipped
+        )?;
+        if dropped > 0 {
+            writeln!(
+                out,
+                "<p><strong>Only the first {} rows are shown; {} more were left out to keep the page usable.</strong></p>",
+                HTML_ROW_CAP, dropped
+            )?;
+            eprintln!(
+                "Warning: {} has only the first {} of {} rows",
+                dest,
+                HTML_ROW_CAP,
+                rows + dropped
+            );
+        }
+        writeln!(out, "<p><input id=\"filter\" type=\"search\" placeholder=\"Filter rows…\" size=\"40\"></p>")?;
+        write!(out, "<table id=\"rows\"><thead><tr>")?;
+        for name in layout.column_names() {
+            write!(out, "<th>{}</th>", xml_escape(name))?;
+        }
+        if layout.tag_cells() {
+            write!(out, "<th class=\"tags\">tags</th>")?;
+        }
+        writeln!(out, "</tr></thead><tbody>")?;
+        out.write_all(body.as_bytes())?;
+        writeln!(out, "</tbody></table>\n<script>{}</script>\n</body></html>", HTML_SCRIPT)?;
+        out.finish()?;
+        Ok((dest, rows))
+    }
+}
+
+/// A GitHub-flavoured Markdown table, with the run stamp and counts as a paragraph above it.
+/// Rows are collected until `finish` so that paragraph can show the final counts.
+pub struct MdOutput {
+    out: Sink,
+    dest: Destination,
+    layout: Layout,
+    body: String,
+    rows: usize,
+    skipped: usize, // rows with an error set
+}
+
+impl MdOutput {
+    pub fn create(dest: &Destination, layout: &Layout) -> Result<MdOutput, Box<dyn Error>> {
+        Ok(MdOutput {
+            out: dest.open(layout.compress)?,
+            dest: dest.clone(),
+            layout: layout.clone(),
+            body: String::new(),
+            rows: 0,
+            skipped: 0,
+        })
+    }
+
+    /// One table line from already-formatted cells.
+    fn line<'a>(&self, cells: impl IntoIterator<Item = &'a str>) -> String {
+        let cells: Vec<String> = cells
+            .into_iter()
+            .map(|c| md_cell(c, self.layout.max_cell_width))
+            .collect();
+        format!("| {} |\n", cells.join(" | "))
+    }
+}
+
+impl RowWriter for MdOutput {
+    fn write_row(&mut self, row: &Row) -> Result<(), Box<dyn Error>> {
+        if row.error.is_some() {
+            self.skipped += 1;
+        }
+        let mut cells = self.layout.cells(row);
+        if self.layout.tag_cells() {
+            let tags: Vec<String> = self.layout.tags(row).map(|tag| self.layout.tag_cell(tag)).collect();
+            cells.push(tags.join("; "));
+        }
+        let line = self.line(cells.iter().map(String::as_str));
+        self.body.push_str(&line);
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(Destination, usize), Box<dyn Error>> {
+        let mut names = self.layout.column_names();
+        if self.layout.tag_cells() {
+            names.push(TAGS_HEADER);
+        }
+        // column names are never cut, only escaped
+        let head: Vec<String> = names.iter().map(|name| md_cell(name, 0)).collect();
+        let head = format!("| {} |\n", head.join(" | "));
+        let rule = format!("|{}\n", " --- |".repeat(names.len()));
+        let MdOutput { mut out, dest, layout, body, rows, skipped } = *self;
+        writeln!(
+            out,
+            "EXIF data for `{}`, created {} (schema version {}): {} images, {} of them not parsed.\n",
+            layout.scan_dir, layout.created_at, SCHEMA_VERSION, rows, skipped
+        )?;
+        out.write_all(head.as_bytes())?;
+        out.write_all(rule.as_bytes())?;
+        out.write_all(body.as_bytes())?;
+        out.finish()?;
+        Ok((dest, rows))
+    }
+}
+
+/// Make a value safe inside a Markdown table cell: pipes escaped, line breaks
+/// turned into `<br>`, and cut to `max_width` characters with "…" (0 = no limit).
+pub fn md_cell(value: &str, max_width: usize) -> String {
+    let value = if max_width > 0 && value.chars().count() > max_width {
+        let mut cut: String = value.chars().take(max_width.saturating_sub(1)).collect();
+        cut.push('…');
+        cut
+    } else {
+        value.to_string()
+    };
+    value
+        .replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\n', '\r'], "<br>")
+}
+
+/// Escape text for use in XML (or HTML) content or attribute values.
+pub fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // control characters other than whitespace are not allowed in XML 1.0 at all
+            c if c < ' ' && !matches!(c, '\t' | '\n' | '\r') => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Turn a free-form value (e.g. a camera model) into something safe in a file name:
+/// runs of anything but ASCII letters, digits, `-` and `.` become a single `_`.
+pub fn sanitize_file_component(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.trim().chars() {
+        if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+            out.push(c);
+        } else if !out.ends_with('_') {
+            out.push('_');
+        }
+    }
+    let out = out.trim_matches(|c| c == '_' || c == '.');
+    if out.is_empty() {
+        "unknown".to_string()
+    } else {
+        out.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create_writer, Destination, Format, Layout, UTF8_BOM};
+    use crate::{config::ScanOptions, testutil::ScratchDir, Column, Row, SCHEMA_VERSION, TAGS_HEADER};
+
+    /// The layout of a run with no options but `format`, as the binary builds it.
+    fn default_layout(format: Format) -> Layout {
+        let opts = ScanOptions::default();
+        Layout {
+            format,
+            columns: opts.fixed_columns(),
+            long: opts.long,
+            created_at: "2024-01-01T00:00:00+00:00".to_string(),
+            scan_dir: ".".to_string(),
+            max_cell_width: opts.max_cell_width,
+            custom_columns: Vec::new(),
+            path_style: opts.path_style(),
+            bom: opts.bom,
+            compress: false,
+            fields: None,
+            other_tags: opts.other_tags,
+            raw_tags: opts.raw_tags,
+        }
+    }
+
+    /// `--print-schema` of a default run, by schema version. Changing a column's
+    /// name, type or place means a new version here and in [`SCHEMA_VERSION`].
+    fn schema_snapshot(version: u32) -> Option<&'static [(&'static str, &'static str)]> {
+        match version {
+            4 => Some(&[
+                ("path", "string"),
+                ("mime", "string"),
+                ("tag_count", "integer"),
+                ("error", "string"),
+                ("orientation", "string"),
+                ("needs_rotation", "boolean"),
+                ("f_number", "number"),
+                ("exposure_seconds", "number"),
+                ("exposure_display", "string"),
+                ("iso", "integer"),
+                ("focal_length_mm", "number"),
+                ("focal_length_35mm", "integer"),
+                ("lens_make", "string"),
+                ("lens_model", "string"),
+                ("lens_serial", "string"),
+                ("mime_mismatch", "boolean"),
+                ("width", "integer"),
+                ("height", "integer"),
+                ("datetime_original_utc", "datetime"),
+                ("datetime_original_tz", "datetime"),
+                ("offset_guessed", "boolean"),
+                ("software", "string"),
+                ("edited", "boolean"),
+                ("aspect_ratio", "number"),
+                ("orientation_class", "string"),
+                ("tags", "tags"),
+            ]),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn schema_changes_bump_the_version() {
+        let schema = default_layout(Format::Csv).schema();
+        assert_eq!(schema["schema_version"], SCHEMA_VERSION);
+        let columns: Vec<(&str, &str)> = schema["columns"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| (c["name"].as_str().unwrap(), c["type"].as_str().unwrap()))
+            .collect();
+        let snapshot = schema_snapshot(SCHEMA_VERSION)
+            .unwrap_or_else(|| panic!("no schema snapshot for version {SCHEMA_VERSION}; add one to schema_snapshot"));
+        assert_eq!(
+            columns, snapshot,
+            "the default columns differ from the snapshot of schema version {SCHEMA_VERSION}: bump SCHEMA_VERSION and add a snapshot for it"
+        );
+    }
+
+    #[test]
+    fn bom_csv_reads_back_with_the_csv_crate() {
+        let dir = ScratchDir::new("bom");
+        let path = dir.path().join("out.csv");
+        let layout = Layout { bom: true, ..default_layout(Format::Csv) };
+        let mut row = Row::skipped("photo.jpg".into(), "");
+        row.error = None;
+        row.lens.model = Some("Sigma 35mm F1.4 DG HSM | Art – Ø 67".to_string());
+        let mut out = create_writer(&Destination::File(path.clone()), &layout).unwrap();
+        out.write_row(&row).unwrap();
+        out.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[..3], UTF8_BOM);
+        let mut reader = csv::ReaderBuilder::new()
+            .comment(Some(b'#'))
+            .flexible(true)
+            .from_path(&path)
+            .unwrap();
+        let header = reader.headers().unwrap().clone();
+        let expected: Vec<&str> = layout.column_names().into_iter().chain([TAGS_HEADER]).collect();
+        assert_eq!(header.iter().collect::<Vec<_>>(), expected);
+        let lens_model = header.iter().position(|name| name == Column::LensModel.name()).unwrap();
+        let record = reader.records().next().unwrap().unwrap();
+        assert_eq!(&record[0], "photo.jpg");
+        assert_eq!(&record[lens_model], "Sigma 35mm F1.4 DG HSM | Art – Ø 67");
+        assert!(reader.records().next().is_none());
+    }
+}
@@ -0,0 +1,147 @@
+//! `--format parquet`: the columns with real types, for pandas, Polars and other
+//! data-frame tools (needs the `parquet` feature).
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::File,
+    io::{self, BufWriter, Write},
+    sync::Arc,
+};
+
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray, TimestampSecondArray};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::DateTime;
+use parquet::arrow::ArrowWriter;
+use serde_json::{Map, Value};
+
+use crate::{
+    output::{Destination, Layout, RowWriter},
+    Row, SCHEMA_VERSION, TAGS_HEADER,
+};
+
+/// Rows gathered before they are written out as one row group.
+const BATCH_ROWS: usize = 65_536;
+
+/// A Parquet file: one typed column per output column (blank cells as nulls)
+/// and, unless `--columns` picked them, the tags as a JSON object string.
+/// The run's `created_at` and `schema_version` go into the file's metadata.
+pub struct ParquetOutput {
+    writer: ArrowWriter<Box<dyn Write + Send>>,
+    schema: Arc<Schema>,
+    types: Vec<&'static str>,  // schema type of each column before the tags
+    pending: Vec<Vec<String>>, // cells of the rows not written yet, column by column
+    tags: Vec<String>,         // their tags, when the layout has tag cells
+    dest: Destination,
+    layout: Layout,
+    rows: usize,
+}
+
+impl ParquetOutput {
+    pub fn create(dest: &Destination, layout: &Layout) -> Result<ParquetOutput, Box<dyn Error>> {
+        let types: Vec<&'static str> = layout.column_types().into_iter().map(|(kind, _)| kind).collect();
+        let mut fields: Vec<Field> = layout
+            .column_names()
+            .into_iter()
+            .zip(&types)
+            .map(|(name, kind)| Field::new(name, data_type(kind), true))
+            .collect();
+        if layout.tag_cells() {
+            fields.push(Field::new(TAGS_HEADER, DataType::Utf8, true));
+        }
+        let metadata = HashMap::from([
+            ("created_at".to_string(), layout.created_at.clone()),
+            ("schema_version".to_string(), SCHEMA_VERSION.to_string()),
+        ]);
+        let schema = Arc::new(Schema::new_with_metadata(fields, metadata));
+        let out: Box<dyn Write + Send> = match dest {
+            Destination::File(path) => Box::new(BufWriter::new(File::create(path)?)),
+            Destination::Append(_) => return Err("a Parquet file can't be appended to".into()),
+            Destination::Stdout => Box::new(BufWriter::new(io::stdout())),
+        };
+        Ok(ParquetOutput {
+            writer: ArrowWriter::try_new(out, schema.clone(), None)?,
+            schema,
+            pending: vec![Vec::new(); types.len()],
+            types,
+            tags: Vec::new(),
+            dest: dest.clone(),
+            layout: layout.clone(),
+            rows: 0,
+        })
+    }
+
+    /// Write the pending rows as one row group.
+    fn flush_batch(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut columns: Vec<ArrayRef> = self
+            .types
+            .iter()
+            .zip(&mut self.pending)
+            .map(|(kind, cells)| typed_column(kind, &std::mem::take(cells)))
+            .collect();
+        if self.layout.tag_cells() {
+            columns.push(Arc::new(StringArray::from(std::mem::take(&mut self.tags))));
+        }
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        if batch.num_rows() > 0 {
+            self.writer.write(&batch)?;
+        }
+        Ok(())
+    }
+}
+
+impl RowWriter for ParquetOutput {
+    fn write_row(&mut self, row: &Row) -> Result<(), Box<dyn Error>> {
+        for (column, cell) in self.pending.iter_mut().zip(self.layout.cells(row)) {
+            column.push(cell);
+        }
+        if self.layout.tag_cells() {
+            let tags: Map<String, Value> = self
+                .layout
+                .tags(row)
+                .map(|tag| (tag.name.clone(), Value::String(tag.value.clone())))
+                .collect();
+            self.tags.push(Value::Object(tags).to_string());
+        }
+        self.rows += 1;
+        if self.rows.is_multiple_of(BATCH_ROWS) {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(Destination, usize), Box<dyn Error>> {
+        self.flush_batch()?;
+        let ParquetOutput { writer, dest, rows, .. } = *self;
+        writer.into_inner()?.flush()?;
+        Ok((dest, rows))
+    }
+}
+
+/// Arrow type for a schema type (see [`crate::Column::schema`]).
+fn data_type(kind: &str) -> DataType {
+    match kind {
+        "integer" => DataType::Int64,
+        "number" => DataType::Float64,
+        "boolean" => DataType::Boolean,
+        "datetime" => DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+        _ => DataType::Utf8,
+    }
+}
+
+/// The cells of one column as an array of [`data_type`]; blank cells, and any
+/// that don't parse as the type, become nulls.
+fn typed_column(kind: &str, cells: &[String]) -> ArrayRef {
+    let present = cells.iter().map(|cell| Some(cell.as_str()).filter(|c| !c.is_empty()));
+    match kind {
+        "integer" => Arc::new(present.map(|c| c?.parse::<i64>().ok()).collect::<Int64Array>()),
+        "number" => Arc::new(present.map(|c| c?.parse::<f64>().ok()).collect::<Float64Array>()),
+        "boolean" => Arc::new(present.map(|c| c?.parse::<bool>().ok()).collect::<BooleanArray>()),
+        "datetime" => {
+            // RFC 3339 with any offset, stored as the instant in UTC
+            let seconds = present.map(|c| Some(DateTime::parse_from_rfc3339(c?).ok()?.timestamp()));
+            Arc::new(seconds.collect::<TimestampSecondArray>().with_timezone("UTC"))
+        }
+        _ => Arc::new(present.collect::<StringArray>()),
+    }
+}
@@ -0,0 +1,118 @@
+//! `--preview`: the first few records as a table for the terminal.
+
+use crate::Row;
+
+/// Widest a column other than the path gets before its values are cut.
+pub(crate) const MAX_COLUMN_WIDTH: usize = 24;
+/// Narrowest the path column is squeezed to before other columns are dropped.
+const MIN_PATH_WIDTH: usize = 16;
+/// Between two columns.
+const GAP: &str = "  ";
+
+/// A preview column: header and how to get the cell from a row.
+pub(crate) struct PreviewColumn {
+    pub(crate) header: &'static str,
+    pub(crate) cell: fn(&Row) -> String,
+}
+
+/// Key columns in the order they are given room; the path always comes first.
+pub(crate) const COLUMNS: &[PreviewColumn] = &[
+    PreviewColumn { header: "path", cell: |row| row.path.to_string_lossy().into_owned() },
+    PreviewColumn { header: "model", cell: |row| row.model.clone().unwrap_or_default() },
+    PreviewColumn {
+        header: "date_taken",
+        cell: |row| row.date_taken.map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_default(),
+    },
+    PreviewColumn { header: "dimensions", cell: dimensions },
+    PreviewColumn { header: "f_number", cell: |row| opt(row.exposure.f_number) },
+    PreviewColumn { header: "exposure", cell: |row| row.exposure.exposure_display.clone().unwrap_or_default() },
+    PreviewColumn { header: "iso", cell: |row| opt(row.exposure.iso) },
+    PreviewColumn { header: "focal_mm", cell: |row| opt(row.exposure.focal_length_mm) },
+    PreviewColumn { header: "error", cell: |row| row.error.clone().unwrap_or_default() },
+];
+
+fn opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// `4000x3000` from PixelX/YDimension, or ImageWidth/ImageLength; blank when unknown.
+fn dimensions(row: &Row) -> String {
+    let width = row.tag_value("PixelXDimension").or_else(|| row.tag_value("ImageWidth"));
+    let height = row.tag_value("PixelYDimension").or_else(|| row.tag_value("ImageLength"));
+    match (width, height) {
+        (Some(w), Some(h)) => format!("{w}x{h}"),
+        _ => String::new(),
+    }
+}
+
+/// Cut `value` to `width` characters, keeping its end ("…/IMG_0001.jpg").
+fn keep_end(value: &str, width: usize) -> String {
+    let len = value.chars().count();
+    if len <= width {
+        return value.to_string();
+    }
+    let tail: String = value.chars().skip(len - width.saturating_sub(1)).collect();
+    format!("…{tail}")
+}
+
+/// Cut `value` to `width` characters, keeping its start.
+fn keep_start(value: &str, width: usize) -> String {
+    if value.chars().count() <= width {
+        return value.to_string();
+    }
+    let head: String = value.chars().take(width.saturating_sub(1)).collect();
+    format!("{head}…")
+}
+
+/// The preview of `rows`. With a known terminal `width` the columns are aligned
+/// and as many as fit are shown; without one every column is printed, tab-separated.
+pub fn preview_table(rows: &[&Row], width: Option<usize>) -> String {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| COLUMNS.iter().map(|c| (c.cell)(row)).collect())
+        .collect();
+    let Some(width) = width else {
+        let mut out = COLUMNS.iter().map(|c| c.header).collect::<Vec<_>>().join("\t") + "\n";
+        for line in &cells {
+            out.push_str(&line.join("\t"));
+            out.push('\n');
+        }
+        return out;
+    };
+
+    // Natural width of each column, then keep columns for as long as they fit
+    let natural: Vec<usize> = (0..COLUMNS.len())
+        .map(|i| {
+            let widest = cells.iter().map(|line| line[i].chars().count()).max().unwrap_or(0);
+            let cap = if i == 0 { usize::MAX } else { MAX_COLUMN_WIDTH };
+            widest.max(COLUMNS[i].header.len()).min(cap)
+        })
+        .collect();
+    let mut widths = vec![0]; // the path's, settled below
+    let mut used = natural[0].min(MIN_PATH_WIDTH);
+    for &w in &natural[1..] {
+        if used + GAP.len() + w > width {
+            break;
+        }
+        used += GAP.len() + w;
+        widths.push(w);
+    }
+    // Whatever is left goes to the path, up to its natural width
+    widths[0] = natural[0].min(MIN_PATH_WIDTH + width.saturating_sub(used));
+
+    let line = |values: Vec<String>| -> String {
+        let padded: Vec<String> = values.into_iter().zip(&widths).map(|(v, &w)| format!("{v:<w$}")).collect();
+        padded.join(GAP).trim_end().to_string() + "\n"
+    };
+    let mut out = line(COLUMNS.iter().zip(&widths).map(|(c, &w)| keep_start(c.header, w)).collect());
+    for values in cells {
+        let shown = values
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (v, &w))| if i == 0 { keep_end(v, w) } else { keep_start(v, w) })
+            .collect();
+        out.push_str(&line(shown));
+    }
+    out
+}
@@ -0,0 +1,46 @@
+//! Camera RAW support (the `raw` feature): find the EXIF inside RAW containers.
+//!
+//! Most RAW formats (CR2, NEF, ARW, DNG, PEF, SRW) are TIFF files underneath and
+//! go to rexif as they are. Olympus ORF and Panasonic RW2 are TIFF with their own
+//! magic number, which is swapped for the standard one, and Fujifilm RAF carries
+//! a full JPEG preview whose EXIF is read instead.
+
+use std::borrow::Cow;
+
+pub use crate::sniff::RAW_EXTENSIONS;
+
+/// Start of a RAF file, followed by version and camera ID fields.
+const RAF_MAGIC: &[u8] = b"FUJIFILMCCD-RAW";
+/// Offset of the big-endian (offset, length) pair locating the JPEG preview in a RAF.
+const RAF_JPEG_POINTER: usize = 84;
+
+/// The part of `data` rexif should parse: `data` itself unless it is one of the
+/// RAW containers rexif can't read directly.
+pub fn exif_container(data: &[u8]) -> Cow<'_, [u8]> {
+    match data {
+        // ORF: "IIRO" / "IIRS" / "MMOR"; RW2: "IIU\0"
+        [b'I', b'I', b'R', b'O' | b'S', ..] | [b'I', b'I', b'U', 0x00, ..] => {
+            Cow::Owned(with_tiff_magic(data, [b'I', b'I', 0x2a, 0x00]))
+        }
+        [b'M', b'M', b'O', b'R', ..] => Cow::Owned(with_tiff_magic(data, [b'M', b'M', 0x00, 0x2a])),
+        _ if data.starts_with(RAF_MAGIC) => raf_preview(data).map_or(Cow::Borrowed(data), Cow::Borrowed),
+        _ => Cow::Borrowed(data),
+    }
+}
+
+fn with_tiff_magic(data: &[u8], magic: [u8; 4]) -> Vec<u8> {
+    let mut patched = data.to_vec();
+    patched[..4].copy_from_slice(&magic);
+    patched
+}
+
+/// The embedded JPEG of a RAF file, `None` when the pointer is out of range.
+fn raf_preview(data: &[u8]) -> Option<&[u8]> {
+    let be_u32 = |at: usize| -> Option<usize> {
+        let bytes: [u8; 4] = data.get(at..at + 4)?.try_into().ok()?;
+        Some(u32::from_be_bytes(bytes) as usize)
+    };
+    let offset = be_u32(RAF_JPEG_POINTER)?;
+    let len = be_u32(RAF_JPEG_POINTER + 4)?;
+    data.get(offset..offset.checked_add(len)?)
+}
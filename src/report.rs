@@ -0,0 +1,70 @@
+//! Reading back the CSV and TSV reports this tool writes (`exif_output.csv`,
+//! `.tsv`, either of them gzipped), e.g. for `verify`.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::Read,
+    path::Path,
+};
+
+use csv::ReaderBuilder;
+use flate2::read::MultiGzDecoder;
+
+use crate::has_extension;
+
+/// A report as written: the metadata of its comment line, the header and one
+/// record per line (tag cells included, so lines differ in length).
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub created_at: Option<String>, // from the `# csv_created_at` line, when there is one
+    pub schema_version: Option<u32>,
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Report {
+    /// Read the report at `path`: tab-separated when the name ends in `.tsv`
+    /// (or `.tsv.gz`), gunzipped when it ends in `.gz`. A byte order mark is
+    /// skipped, and so is the comment line when it is missing.
+    pub fn read(path: &Path) -> Result<Report, Box<dyn Error>> {
+        let file = File::open(path)?;
+        let gzipped = has_extension(path, &["gz"]);
+        let input: Box<dyn Read> = if gzipped { Box::new(MultiGzDecoder::new(file)) } else { Box::new(file) };
+        let name = if gzipped { path.with_extension("") } else { path.to_path_buf() };
+        let delimiter = if has_extension(&name, &["tsv"]) { b'\t' } else { b',' };
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(delimiter)
+            .from_reader(input);
+        let mut records = reader.records();
+        let mut first = records.next().ok_or("the report is empty")??;
+        let mut report = Report::default();
+        if let Some(comment) = first.get(0).and_then(|cell| cell.strip_prefix("# ")) {
+            for part in comment.split("; ") {
+                match part.split_once(": ") {
+                    Some(("csv_created_at", stamp)) => report.created_at = Some(stamp.to_string()),
+                    Some(("schema_version", version)) => report.schema_version = version.parse().ok(),
+                    _ => {}
+                }
+            }
+            first = records.next().ok_or("the report has no header row")??;
+        }
+        report.header = first.iter().map(String::from).collect();
+        for record in records {
+            report.rows.push(record?.iter().map(String::from).collect());
+        }
+        Ok(report)
+    }
+
+    /// Position of the column called `name`, if the report has it.
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.header.iter().position(|h| h == name)
+    }
+
+    /// Cell `column` of `row`; `None` when it is blank or the line is short.
+    pub fn cell<'a>(&self, row: &'a [String], column: Option<usize>) -> Option<&'a str> {
+        row.get(column?).map(String::as_str).filter(|cell| !cell.is_empty())
+    }
+}
@@ -0,0 +1,686 @@
+//! The per-file record and the CSV columns it can be flattened into.
+
+use std::{borrow::Cow, path::PathBuf};
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone, Utc};
+use rexif::{ExifEntry, ExifTag, TagValue};
+
+use crate::{
+    fields, organize,
+    sniff::{ImageType, RAW_EXTENSIONS},
+    tags::Tag,
+};
+
+/// Numeric IDs of the EXIF 2.31 offset tags, which rexif has no names for.
+const OFFSET_TIME: u16 = 0x9010;
+const OFFSET_TIME_ORIGINAL: u16 = 0x9011;
+
+/// A fixed CSV column; the variable-length "tag: value" cells always follow these.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Column {
+    Path,
+    Mime,
+    TagCount,
+    Error,
+    Orientation,   // readable EXIF orientation
+    NeedsRotation, // orientation is anything but "Normal"
+    FNumber,
+    ExposureSeconds,
+    ExposureDisplay,
+    Iso,
+    FocalLengthMm,
+    FocalLength35mm,
+    LensMake,
+    LensModel,
+    LensSerial,
+    ClaimedExt,   // extension in the file name (--sniff)
+    DetectedType, // type according to the file's magic bytes (--sniff)
+    TypeMismatch, // the two above disagree (--sniff)
+    OrganizedPath,  // where --organize put (or would put) the file
+    OrganizeAction, // what --organize did with it
+    MissingTags,    // --require-tags that the file lacks
+    FileSize,       // bytes, as read
+    GpsLatitude,    // decimal degrees, north positive
+    GpsLongitude,   // decimal degrees, east positive
+    DateDriftSeconds, // file mtime minus EXIF DateTimeOriginal (--date-drift)
+    DateDrift,        // the two differ by more than the --date-drift threshold
+    ApertureDisplay, // f_number as "f/2.8" (--pretty-exposure)
+    ShutterDisplay,  // exposure_seconds as "1/250" or "2s" (--pretty-exposure)
+    MimeMismatch,    // the extension doesn't fit the MIME type rexif found
+    Width,  // pixels, from the EXIF size tags or else the JPEG frame header
+    Height, // pixels, likewise
+    DatetimeOriginalUtc, // DateTimeOriginal moved to UTC by its offset tag (or --assume-offset)
+    DatetimeOriginalTz, // DateTimeOriginal with its UTC offset, RFC 3339
+    OffsetGuessed,      // that offset is not from the file: --assume-offset, or this machine's zone
+    Software, // Software tag, else ProcessingSoftware
+    Edited,   // that names an editor rather than camera firmware (a guess)
+    AspectRatio,      // width / height as displayed, after the Orientation rotation
+    OrientationClass, // "portrait", "landscape" or "square", likewise
+    MtimeBefore, // the file's modification time before --touch, UTC
+    MtimeAfter,  // the one --touch set (or would set, with --dry-run)
+    TouchAction, // what --touch did with the file
+}
+
+impl Column {
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Path => "path",
+            Column::Mime => "mime",
+            Column::TagCount => "tag_count",
+            Column::Error => "error",
+            Column::Orientation => "orientation",
+            Column::NeedsRotation => "needs_rotation",
+            Column::FNumber => "f_number",
+            Column::ExposureSeconds => "exposure_seconds",
+            Column::ExposureDisplay => "exposure_display",
+            Column::Iso => "iso",
+            Column::FocalLengthMm => "focal_length_mm",
+            Column::FocalLength35mm => "focal_length_35mm",
+            Column::LensMake => "lens_make",
+            Column::LensModel => "lens_model",
+            Column::LensSerial => "lens_serial",
+            Column::ClaimedExt => "claimed_ext",
+            Column::DetectedType => "detected_type",
+            Column::TypeMismatch => "type_mismatch",
+            Column::OrganizedPath => "organized_path",
+            Column::OrganizeAction => "organize_action",
+            Column::MissingTags => "missing_tags",
+            Column::FileSize => "file_size",
+            Column::GpsLatitude => "gps_latitude",
+            Column::GpsLongitude => "gps_longitude",
+            Column::DateDriftSeconds => "date_drift_seconds",
+            Column::DateDrift => "date_drift",
+            Column::ApertureDisplay => "aperture_display",
+            Column::ShutterDisplay => "shutter_display",
+            Column::MimeMismatch => "mime_mismatch",
+            Column::Width => "width",
+            Column::Height => "height",
+            Column::DatetimeOriginalUtc => "datetime_original_utc",
+            Column::DatetimeOriginalTz => "datetime_original_tz",
+            Column::OffsetGuessed => "offset_guessed",
+            Column::Software => "software",
+            Column::Edited => "edited",
+            Column::AspectRatio => "aspect_ratio",
+            Column::OrientationClass => "orientation_class",
+            Column::MtimeBefore => "mtime_before",
+            Column::MtimeAfter => "mtime_after",
+            Column::TouchAction => "touch_action",
+        }
+    }
+
+    /// The column's value type and a one-line description, for `--print-schema`.
+    /// Types are `string`, `integer`, `number`, `boolean` and `datetime`; any
+    /// cell may also be blank when the file doesn't say.
+    pub fn schema(self) -> (&'static str, &'static str) {
+        match self {
+            Column::Path => ("string", "path of the image, as found or as --absolute-paths / --hash-paths show it"),
+            Column::Mime => ("string", "MIME type rexif detected"),
+            Column::TagCount => ("integer", "number of EXIF tags read"),
+            Column::Error => ("string", "why the file was not parsed, e.g. \"skipped: too large\""),
+            Column::Orientation => ("string", "EXIF orientation, e.g. \"Rotate 90 CW\""),
+            Column::NeedsRotation => ("boolean", "the orientation is anything but Normal"),
+            Column::FNumber => ("number", "aperture f-number"),
+            Column::ExposureSeconds => ("number", "exposure time in seconds"),
+            Column::ExposureDisplay => ("string", "exposure time as shown on cameras, e.g. 1/250"),
+            Column::Iso => ("integer", "ISO speed"),
+            Column::FocalLengthMm => ("number", "focal length in millimetres"),
+            Column::FocalLength35mm => ("integer", "35 mm equivalent focal length"),
+            Column::LensMake => ("string", "lens maker"),
+            Column::LensModel => ("string", "lens model"),
+            Column::LensSerial => ("string", "lens serial number"),
+            Column::ClaimedExt => ("string", "extension in the file name, lower case"),
+            Column::DetectedType => ("string", "type according to the file's magic bytes"),
+            Column::TypeMismatch => ("boolean", "the extension and the detected type disagree"),
+            Column::OrganizedPath => ("string", "where --organize put (or would put) the file"),
+            Column::OrganizeAction => ("string", "what --organize did with the file"),
+            Column::MissingTags => ("string", "--require-tags the file lacks, joined by ';'"),
+            Column::FileSize => ("integer", "file size in bytes"),
+            Column::GpsLatitude => ("number", "latitude in decimal degrees, north positive"),
+            Column::GpsLongitude => ("number", "longitude in decimal degrees, east positive"),
+            Column::DateDriftSeconds => ("integer", "file mtime minus DateTimeOriginal, in seconds"),
+            Column::DateDrift => ("boolean", "the two differ by more than the --date-drift threshold"),
+            Column::ApertureDisplay => ("string", "f-number as f/2.8"),
+            Column::ShutterDisplay => ("string", "exposure time as 1/250 or 2s"),
+            Column::MimeMismatch => ("boolean", "the extension doesn't fit the MIME type"),
+            Column::Width => ("integer", "width in pixels"),
+            Column::Height => ("integer", "height in pixels"),
+            Column::DatetimeOriginalUtc => ("datetime", "DateTimeOriginal in UTC, as 2024-05-01T10:00:00Z"),
+            Column::DatetimeOriginalTz => ("datetime", "DateTimeOriginal with its UTC offset, as 2024-05-01T12:00:00+02:00"),
+            Column::OffsetGuessed => ("boolean", "the offset above is not from the file's OffsetTime tags"),
+            Column::Software => ("string", "Software tag (or ProcessingSoftware): camera firmware or the editor that saved the file"),
+            Column::Edited => ("boolean", "the software looks like an editor rather than camera firmware (a heuristic)"),
+            Column::AspectRatio => ("number", "width / height as displayed, i.e. after the Orientation rotation"),
+            Column::OrientationClass => ("string", "portrait, landscape or square as displayed"),
+            Column::MtimeBefore => ("datetime", "the file's modification time before --touch, in UTC"),
+            Column::MtimeAfter => ("datetime", "the modification time --touch set, or would set with --dry-run"),
+            Column::TouchAction => ("string", "what --touch did, e.g. \"touched\" or \"skipped: read-only\""),
+        }
+    }
+
+    /// The column called `name`, if there is one.
+    pub fn from_name(name: &str) -> Option<Column> {
+        ALL_COLUMNS.iter().copied().find(|c| c.name() == name)
+    }
+}
+
+/// Version of the output layout: the names, order and meaning of the columns.
+/// Bump it whenever one of those changes, so downstream readers can tell.
+/// A test in `output.rs` pins the default columns of each version.
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// Columns every CSV starts with. Their order is part of the schema: new
+/// columns go at the end, and [`SCHEMA_VERSION`] goes up.
+pub const BASE_COLUMNS: [Column; 25] = [
+    Column::Path,
+    Column::Mime,
+    Column::TagCount,
+    Column::Error,
+    Column::Orientation,
+    Column::NeedsRotation,
+    Column::FNumber,
+    Column::ExposureSeconds,
+    Column::ExposureDisplay,
+    Column::Iso,
+    Column::FocalLengthMm,
+    Column::FocalLength35mm,
+    Column::LensMake,
+    Column::LensModel,
+    Column::LensSerial,
+    Column::MimeMismatch,
+    Column::Width,
+    Column::Height,
+    Column::DatetimeOriginalUtc,
+    Column::DatetimeOriginalTz,
+    Column::OffsetGuessed,
+    Column::Software,
+    Column::Edited,
+    Column::AspectRatio,
+    Column::OrientationClass,
+];
+
+/// Every fixed column, in the order `--columns` lists them when a name is wrong.
+pub const ALL_COLUMNS: [Column; 41] = [
+    Column::Path,
+    Column::Mime,
+    Column::MimeMismatch,
+    Column::FileSize,
+    Column::Width,
+    Column::Height,
+    Column::AspectRatio,
+    Column::OrientationClass,
+    Column::DatetimeOriginalUtc,
+    Column::DatetimeOriginalTz,
+    Column::OffsetGuessed,
+    Column::TagCount,
+    Column::Error,
+    Column::Orientation,
+    Column::NeedsRotation,
+    Column::FNumber,
+    Column::ExposureSeconds,
+    Column::ExposureDisplay,
+    Column::Iso,
+    Column::FocalLengthMm,
+    Column::FocalLength35mm,
+    Column::ApertureDisplay,
+    Column::ShutterDisplay,
+    Column::LensMake,
+    Column::LensModel,
+    Column::LensSerial,
+    Column::Software,
+    Column::Edited,
+    Column::GpsLatitude,
+    Column::GpsLongitude,
+    Column::ClaimedExt,
+    Column::DetectedType,
+    Column::TypeMismatch,
+    Column::OrganizedPath,
+    Column::OrganizeAction,
+    Column::MtimeBefore,
+    Column::MtimeAfter,
+    Column::TouchAction,
+    Column::MissingTags,
+    Column::DateDriftSeconds,
+    Column::DateDrift,
+];
+
+/// Header cell labelling the "tag: value" cells after the fixed columns.
+pub const TAGS_HEADER: &str = "tags";
+
+/// Header row for a CSV made of `columns` followed by the tag cells.
+pub fn header(columns: &[Column]) -> Vec<&'static str> {
+    columns
+        .iter()
+        .map(|c| c.name())
+        .chain([TAGS_HEADER])
+        .collect()
+}
+
+/// Header of the `--long` output: one line per tag occurrence.
+pub const LONG_HEADER: [&str; 3] = ["path", "tag", "value"];
+
+/// Extra `--long` columns with `--raw-tags`: hex ID, data type, unconverted value.
+pub const LONG_RAW_HEADER: [&str; 3] = ["id", "type", "raw"];
+
+/// A cell for an optional value: blank when absent.
+fn opt_cell<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// What [`Row::sanitize`] does with control characters in text values; NULs are
+/// always dropped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sanitize {
+    pub escape: bool,        // write other controls as `\x07` instead of a space (`--raw-values`)
+    pub keep_newlines: bool, // leave line breaks in (as `\n`) for formats that can show them
+}
+
+impl Sanitize {
+    /// `text` with its control characters dealt with; borrowed when there were none.
+    pub fn value(self, text: &str) -> Cow<'_, str> {
+        if !text.chars().any(|c| c.is_ascii_control()) {
+            return Cow::Borrowed(text);
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\0' => {}
+                '\r' | '\n' => {
+                    // a CR LF pair, or a whole run of line breaks, counts as one
+                    while chars.next_if(|&c| c == '\r' || c == '\n').is_some() {}
+                    out.push(if self.keep_newlines { '\n' } else { ' ' });
+                }
+                c if c.is_ascii_control() && self.escape => out.push_str(&format!("\\x{:02x}", c as u32)),
+                c if c.is_ascii_control() => out.push(' '),
+                c => out.push(c),
+            }
+        }
+        Cow::Owned(out)
+    }
+}
+
+/// EXIF data for one file, as written to one CSV line.
+pub struct Row {
+    pub path: PathBuf,
+    pub mime: String,
+    pub file_size: Option<u64>,                // bytes; None when the size could not be read
+    pub tags: Vec<Tag>,                        // in file order
+    pub date_taken: Option<NaiveDateTime>,     // parsed DateTimeOriginal, if usable
+    pub offset: Option<FixedOffset>,           // OffsetTimeOriginal, else OffsetTime; the binary's --assume-offset fills gaps
+    pub offset_assumed: bool,                  // `offset` came from --assume-offset, not the file
+    pub model: Option<String>,                 // camera Model tag, trimmed
+    pub software: Option<String>,              // Software tag, else ProcessingSoftware, trimmed
+    pub error: Option<String>,                 // why the file was not parsed, e.g. "skipped: too large"
+    pub detected_type: Option<ImageType>,      // from the file's leading bytes
+    pub orientation: Option<u16>,              // raw EXIF Orientation value
+    pub size: Option<(u32, u32)>,              // (width, height) in pixels
+    pub exposure: fields::Exposure,            // aperture, shutter, ISO, focal length as numbers
+    pub gps: Option<(f64, f64)>,               // (latitude, longitude) in decimal degrees
+    pub lens: fields::Lens,                    // LensMake / LensModel / LensSerialNumber
+    pub organized_path: Option<PathBuf>,       // filled in by the binary's --organize
+    pub organize_action: Option<String>,       // e.g. "copied", "would move", "skipped: no EXIF date"
+    pub mtime_before: Option<DateTime<Utc>>,   // filled in by the binary's --touch
+    pub mtime_after: Option<DateTime<Utc>>,    // the mtime --touch set, or would set
+    pub touch_action: Option<String>,          // e.g. "touched", "would touch", "skipped: read-only"
+    pub custom: Vec<(String, Option<String>)>, // (name, value) from the registered ColumnExtractors
+    pub missing_tags: Option<Vec<String>>,     // filled in by the binary's --require-tags
+    pub date_drift: Option<(i64, bool)>,       // filled in by the binary's --date-drift: (seconds, over the threshold)
+}
+
+impl Row {
+    /// Placeholder row for a file that was deliberately not parsed.
+    pub fn skipped(path: PathBuf, reason: &str) -> Row {
+        Row {
+            path,
+            mime: String::new(),
+            file_size: None,
+            tags: Vec::new(),
+            date_taken: None,
+            offset: None,
+            offset_assumed: false,
+            model: None,
+            software: None,
+            error: Some(reason.to_string()),
+            detected_type: None,
+            orientation: None,
+            size: None,
+            exposure: fields::Exposure::default(),
+            gps: None,
+            lens: fields::Lens::default(),
+            organized_path: None,
+            organize_action: None,
+            mtime_before: None,
+            mtime_after: None,
+            touch_action: None,
+            custom: Vec::new(),
+            missing_tags: None,
+            date_drift: None,
+        }
+    }
+
+    /// Build a row from parsed (and de-duplicated) EXIF entries; shared by the
+    /// path- and byte-based entry points.
+    pub(crate) fn from_exif(path: PathBuf, mime: &str, entries: &[&ExifEntry], detected_type: Option<ImageType>) -> Row {
+        let mut date_taken = None;
+        let (mut offset_original, mut offset) = (None, None);
+        let mut model = None;
+        let (mut software, mut processing_software) = (None, None);
+        let mut orientation = None;
+        let mut exposure = fields::Exposure::default();
+        let mut dimensions = fields::Dimensions::default();
+        let mut gps = fields::GpsParts::default();
+        let mut lens = fields::Lens::default();
+        let mut tags = Vec::with_capacity(entries.len());
+        for &entry in entries {
+            if let (ExifTag::DateTimeOriginal, TagValue::Ascii(s)) = (entry.tag, &entry.value) {
+                date_taken = organize::parse_exif_datetime(s);
+            }
+            if entry.tag == ExifTag::Model {
+                model = fields::ascii(&entry.value);
+            }
+            if entry.tag == ExifTag::Software {
+                software = fields::ascii(&entry.value);
+            }
+            if entry.tag == ExifTag::UnknownToMe && entry.ifd.tag == fields::PROCESSING_SOFTWARE {
+                processing_software = fields::ascii(&entry.value);
+            }
+            if let (ExifTag::UnknownToMe, TagValue::Ascii(s)) = (entry.tag, &entry.value) {
+                match entry.ifd.tag {
+                    OFFSET_TIME_ORIGINAL => offset_original = fields::parse_offset(s),
+                    OFFSET_TIME => offset = fields::parse_offset(s),
+                    _ => {}
+                }
+            }
+            if entry.tag == ExifTag::Orientation {
+                orientation = entry.value.to_i64(0).and_then(|v| u16::try_from(v).ok());
+            }
+            exposure.add(entry.tag, &entry.value);
+            dimensions.add(entry);
+            gps.add(entry.tag, &entry.value);
+            lens.add(entry);
+            tags.push(Tag::from_entry(entry));
+        }
+        Row {
+            path,
+            mime: mime.to_string(),
+            file_size: None,
+            tags,
+            date_taken,
+            offset: offset_original.or(offset),
+            offset_assumed: false,
+            model,
+            software: software.or(processing_software),
+            error: None,
+            detected_type,
+            orientation,
+            size: dimensions.size(),
+            exposure,
+            gps: gps.position(),
+            lens,
+            organized_path: None,
+            organize_action: None,
+            mtime_before: None,
+            mtime_after: None,
+            touch_action: None,
+            custom: Vec::new(),
+            missing_tags: None,
+            date_drift: None,
+        }
+    }
+
+    /// Clean every text value taken from the file (tag values, model, lens,
+    /// custom columns) so no output format has to cope with control characters.
+    pub fn sanitize(&mut self, how: Sanitize) {
+        let clean = |value: &mut String| {
+            if let Cow::Owned(cleaned) = how.value(value) {
+                *value = cleaned;
+            }
+        };
+        for tag in &mut self.tags {
+            clean(&mut tag.value);
+            clean(&mut tag.raw);
+        }
+        let lens = [&mut self.lens.make, &mut self.lens.model, &mut self.lens.serial];
+        for value in lens.into_iter().chain([&mut self.model, &mut self.software]).flatten() {
+            clean(value);
+        }
+        for value in self.custom.iter_mut().filter_map(|(_, value)| value.as_mut()) {
+            clean(value);
+        }
+    }
+
+    /// Extension the file name claims, lower-cased ("" when there is none).
+    pub fn claimed_ext(&self) -> String {
+        self.path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default()
+    }
+
+    /// Whether the file name's extension is wrong for the MIME type rexif reported,
+    /// e.g. a TIFF called `.jpg`; `None` for unparsed files, unknown types, and
+    /// RAW files, whose extension never names the container.
+    pub fn mime_mismatch(&self) -> Option<bool> {
+        let kind = ImageType::from_mime(&self.mime)?;
+        let ext = self.claimed_ext();
+        if RAW_EXTENSIONS.contains(&ext.as_str()) {
+            return None;
+        }
+        Some(!kind.matches_extension(&ext))
+    }
+
+    /// DateTimeOriginal in UTC; `None` without both the date and an offset.
+    pub fn taken_utc(&self) -> Option<NaiveDateTime> {
+        let offset = self.offset?;
+        Some(self.date_taken? - chrono::Duration::seconds(i64::from(offset.local_minus_utc())))
+    }
+
+    /// DateTimeOriginal as a moment in time: at its offset, or else read as this
+    /// machine's local time (see [`Row::offset_guessed`]). `None` without the date.
+    pub fn taken_at(&self) -> Option<DateTime<FixedOffset>> {
+        let taken = self.date_taken?;
+        match self.offset {
+            Some(offset) => offset.from_local_datetime(&taken).single(),
+            // a time skipped by a DST change has no reading; a repeated one takes the first
+            None => Local.from_local_datetime(&taken).earliest().map(|t| t.fixed_offset()),
+        }
+    }
+
+    /// Whether [`Row::taken_at`]'s offset is a guess rather than the file's own
+    /// offset tag; `None` without the date.
+    pub fn offset_guessed(&self) -> Option<bool> {
+        self.date_taken?;
+        Some(self.offset.is_none() || self.offset_assumed)
+    }
+
+    /// `(width, height)` as viewers show the image: swapped when the Orientation
+    /// tag turns it a quarter (values 5–8), so a landscape readout can be a
+    /// portrait photo. `None` without a size, or with a zero side.
+    pub fn display_size(&self) -> Option<(u32, u32)> {
+        let (width, height) = self.size.filter(|&(width, height)| width > 0 && height > 0)?;
+        match self.orientation {
+            Some(5..=8) => Some((height, width)),
+            _ => Some((width, height)),
+        }
+    }
+
+    /// Displayed width over height, rounded to three decimals.
+    pub fn aspect_ratio(&self) -> Option<f64> {
+        let (width, height) = self.display_size()?;
+        Some((f64::from(width) / f64::from(height) * 1000.0).round() / 1000.0)
+    }
+
+    /// "portrait", "landscape", or "square" when the sides are within 1% of
+    /// each other (a 4000×3990 crop still counts as square).
+    pub fn orientation_class(&self) -> Option<&'static str> {
+        let (width, height) = self.display_size()?;
+        let (long, short) = (width.max(height), width.min(height));
+        Some(if f64::from(long - short) <= f64::from(long) * 0.01 {
+            "square"
+        } else if width > height {
+            "landscape"
+        } else {
+            "portrait"
+        })
+    }
+
+    /// Readable orientation, `None` when the tag is missing or holds garbage.
+    pub fn orientation_label(&self) -> Option<&'static str> {
+        self.orientation.and_then(fields::orientation_label)
+    }
+
+    /// Whether the file has the tag a user calls `wanted` (see [`Tag::matches`]).
+    pub fn has_tag(&self, wanted: &str) -> bool {
+        self.tags.iter().any(|tag| tag.matches(wanted))
+    }
+
+    /// Readable value of the first tag a user calls `wanted`.
+    pub fn tag_value(&self, wanted: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|tag| tag.matches(wanted))
+            .map(|tag| tag.value.as_str())
+    }
+
+    /// Value of the custom column `name`; `None` when blank or not registered.
+    pub fn custom_value(&self, name: &str) -> Option<&str> {
+        self.custom
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// Value of one fixed column for this row.
+    pub fn cell(&self, column: Column) -> String {
+        match column {
+            Column::Path => self.path.to_string_lossy().into_owned(), // full path / filename
+            Column::Mime => self.mime.clone(),
+            Column::TagCount => match self.error {
+                None => self.tags.len().to_string(),
+                Some(_) => String::new(), // not parsed, so no count
+            },
+            Column::Error => self.error.clone().unwrap_or_default(),
+            Column::Orientation => self.orientation_label().unwrap_or_default().to_string(),
+            Column::NeedsRotation => match self.orientation_label() {
+                Some(_) => (self.orientation != Some(1)).to_string(),
+                None => String::new(), // missing or invalid orientation
+            },
+            Column::FNumber => opt_cell(self.exposure.f_number),
+            Column::ExposureSeconds => opt_cell(self.exposure.exposure_seconds),
+            Column::ExposureDisplay => self.exposure.exposure_display.clone().unwrap_or_default(),
+            Column::Iso => opt_cell(self.exposure.iso),
+            Column::FocalLengthMm => opt_cell(self.exposure.focal_length_mm),
+            Column::FocalLength35mm => opt_cell(self.exposure.focal_length_35mm),
+            Column::LensMake => self.lens.make.clone().unwrap_or_default(),
+            Column::LensModel => self.lens.model.clone().unwrap_or_default(),
+            Column::LensSerial => self.lens.serial.clone().unwrap_or_default(),
+            Column::ClaimedExt => self.claimed_ext(),
+            Column::DetectedType => self.detected_type.map(|t| t.as_str().to_string()).unwrap_or_default(),
+            Column::TypeMismatch => match self.detected_type {
+                Some(t) => (!t.matches_extension(&self.claimed_ext())).to_string(),
+                None => String::new(),
+            },
+            Column::OrganizedPath => opt_cell(self.organized_path.as_ref().map(|p| p.display())),
+            Column::OrganizeAction => self.organize_action.clone().unwrap_or_default(),
+            Column::MissingTags => self.missing_tags.as_ref().map(|m| m.join(";")).unwrap_or_default(),
+            Column::FileSize => opt_cell(self.file_size),
+            Column::GpsLatitude => opt_cell(self.gps.map(|(lat, _)| lat)),
+            Column::GpsLongitude => opt_cell(self.gps.map(|(_, lon)| lon)),
+            Column::DateDriftSeconds => opt_cell(self.date_drift.map(|(seconds, _)| seconds)),
+            Column::DateDrift => opt_cell(self.date_drift.map(|(_, drifted)| drifted)),
+            Column::ApertureDisplay => self.exposure.aperture_label().unwrap_or_default(),
+            Column::ShutterDisplay => self.exposure.shutter_label().unwrap_or_default(),
+            Column::MimeMismatch => opt_cell(self.mime_mismatch()),
+            Column::Width => opt_cell(self.size.map(|(width, _)| width)),
+            Column::Height => opt_cell(self.size.map(|(_, height)| height)),
+            Column::DatetimeOriginalUtc => opt_cell(self.taken_utc().map(|t| t.format("%Y-%m-%dT%H:%M:%SZ"))),
+            Column::DatetimeOriginalTz => opt_cell(self.taken_at().map(|t| t.format("%Y-%m-%dT%H:%M:%S%:z"))),
+            Column::OffsetGuessed => opt_cell(self.offset_guessed()),
+            Column::Software => self.software.clone().unwrap_or_default(),
+            Column::Edited => opt_cell(self.software.as_deref().map(fields::looks_edited)),
+            Column::AspectRatio => opt_cell(self.aspect_ratio()),
+            Column::OrientationClass => self.orientation_class().unwrap_or_default().to_string(),
+            Column::MtimeBefore => opt_cell(self.mtime_before.map(|t| t.format("%Y-%m-%dT%H:%M:%SZ"))),
+            Column::MtimeAfter => opt_cell(self.mtime_after.map(|t| t.format("%Y-%m-%dT%H:%M:%SZ"))),
+            Column::TouchAction => self.touch_action.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Flatten into CSV cells: the fixed `columns`, then one "tag: value" cell per tag.
+    pub fn to_record(&self, columns: &[Column]) -> Vec<String> {
+        let mut record = Vec::with_capacity(columns.len() + self.tags.len());
+        record.extend(columns.iter().map(|&c| self.cell(c)));
+        for tag in &self.tags {
+            record.push(format!("{}: {}", tag.label, tag.value));
+        }
+        record
+    }
+
+    /// `--long` layout: one `[path, tag, value]` record per tag, in file order.
+    pub fn to_long_records(&self) -> impl Iterator<Item = [String; 3]> + '_ {
+        let path = self.path.to_string_lossy();
+        self.tags
+            .iter()
+            .map(move |tag| [path.to_string(), tag.label.clone(), tag.value.clone()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use rexif::{ExifTag, IfdKind, TagValue};
+
+    use super::{Row, Sanitize};
+    use crate::testutil::entry;
+
+    const PLAIN: Sanitize = Sanitize { escape: false, keep_newlines: false };
+    const RAW_VALUES: Sanitize = Sanitize { escape: true, keep_newlines: false };
+
+    #[test]
+    fn clean_text_is_borrowed() {
+        for how in [PLAIN, RAW_VALUES] {
+            assert!(matches!(how.value("Canon EOS R5 – ü"), std::borrow::Cow::Borrowed(_)));
+        }
+    }
+
+    #[test]
+    fn nul_is_dropped_in_both_modes() {
+        for how in [PLAIN, RAW_VALUES] {
+            assert_eq!(how.value("Canon\0\0"), "Canon");
+            assert_eq!(how.value("a\0b"), "ab");
+        }
+    }
+
+    #[test]
+    fn other_controls_become_spaces_or_escapes() {
+        assert_eq!(PLAIN.value("bell\x07tab\tesc\x1bdel\x7f"), "bell tab esc del ");
+        assert_eq!(RAW_VALUES.value("bell\x07tab\tesc\x1bdel\x7f"), "bell\\x07tab\\x09esc\\x1bdel\\x7f");
+    }
+
+    #[test]
+    fn line_breaks_collapse_unless_kept() {
+        for how in [PLAIN, RAW_VALUES] {
+            assert_eq!(how.value("one\r\ntwo\n\n\rthree\rfour"), "one two three four");
+            let kept = Sanitize { keep_newlines: true, ..how };
+            assert_eq!(kept.value("one\r\ntwo\n\n\rthree\rfour"), "one\ntwo\nthree\nfour");
+        }
+    }
+
+    #[test]
+    fn sanitize_cleans_every_text_field() {
+        let entries = [
+            entry(ExifTag::Model, IfdKind::Ifd0, TagValue::Ascii("EOS\0 R5\n".into()), "EOS\0 R5\n"),
+            entry(ExifTag::Software, IfdKind::Ifd0, TagValue::Ascii("v1\x07".into()), "v1\x07"),
+            entry(ExifTag::LensModel, IfdKind::Exif, TagValue::Ascii("RF\t50mm".into()), "RF\t50mm"),
+        ];
+        let entries: Vec<_> = entries.iter().collect();
+        let mut row = Row::from_exif(PathBuf::from("a.jpg"), "image/jpeg", &entries, None);
+        row.sanitize(RAW_VALUES);
+        assert_eq!(row.model.as_deref(), Some("EOS R5"));
+        assert_eq!(row.software.as_deref(), Some("v1\\x07"));
+        assert_eq!(row.lens.model.as_deref(), Some("RF\\x0950mm"));
+        assert_eq!(row.tags[0].value, "EOS R5 ");
+        assert!(row.tags.iter().all(|tag| !tag.raw.chars().any(|c| c.is_ascii_control())));
+    }
+}
@@ -0,0 +1,143 @@
+//! `--sniff` support: recognise images by their magic numbers instead of their names.
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+
+/// Bytes needed to tell the supported formats apart (HEIC's brand ends at offset 12).
+const SNIFF_LEN: usize = 16;
+
+/// File extensions (lower case) of camera RAW formats, picked up by `--raw` in
+/// builds with the `raw` feature; their containers are never named after their type.
+pub const RAW_EXTENSIONS: &[&str] = &["cr2", "nef", "nrw", "arw", "dng", "orf", "rw2", "raf", "pef", "srw"];
+
+/// Image container detected from a file's leading bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageType {
+    Jpeg,
+    Tiff,
+    Png,
+    Heic,
+}
+
+impl ImageType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImageType::Jpeg => "jpeg",
+            ImageType::Tiff => "tiff",
+            ImageType::Png => "png",
+            ImageType::Heic => "heic",
+        }
+    }
+
+    /// The type behind a MIME type as rexif reports it (`image/jpeg`, `image/tiff`).
+    pub fn from_mime(mime: &str) -> Option<ImageType> {
+        match mime {
+            "image/jpeg" => Some(ImageType::Jpeg),
+            "image/tiff" => Some(ImageType::Tiff),
+            "image/png" => Some(ImageType::Png),
+            "image/heic" | "image/heif" => Some(ImageType::Heic),
+            _ => None,
+        }
+    }
+
+    /// Whether rexif can read EXIF from this container (PNG and HEIC are only recognised).
+    pub fn has_exif_reader(self) -> bool {
+        matches!(self, ImageType::Jpeg | ImageType::Tiff)
+    }
+
+    /// Whether `ext` (without the dot, any case) is a usual name for this type.
+    pub fn matches_extension(self, ext: &str) -> bool {
+        let known: &[&str] = match self {
+            ImageType::Jpeg => &["jpg", "jpeg", "jpe", "jfif"],
+            ImageType::Tiff => &["tif", "tiff"],
+            ImageType::Png => &["png"],
+            ImageType::Heic => &["heic", "heif", "hif"],
+        };
+        known.iter().any(|k| ext.eq_ignore_ascii_case(k))
+    }
+}
+
+/// Identify an image from the start of its contents.
+pub fn sniff_bytes(head: &[u8]) -> Option<ImageType> {
+    match head {
+        [0xff, 0xd8, 0xff, ..] => Some(ImageType::Jpeg),
+        [b'I', b'I', 0x2a, 0x00, ..] | [b'M', b'M', 0x00, 0x2a, ..] => Some(ImageType::Tiff),
+        [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, ..] => Some(ImageType::Png),
+        [_, _, _, _, b'f', b't', b'y', b'p', brand @ ..] if brand.len() >= 4 => {
+            match &brand[..4] {
+                b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" | b"mif1" | b"msf1" => {
+                    Some(ImageType::Heic)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// `(width, height)` from a JPEG's frame header (the SOFn segment), for files
+/// whose EXIF doesn't record the size. `None` if the markers run out first.
+pub fn jpeg_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let mut pos = 2; // past SOI
+    loop {
+        // markers may be padded with any number of 0xff fill bytes
+        while data.get(pos) == Some(&0xff) && data.get(pos + 1) == Some(&0xff) {
+            pos += 1;
+        }
+        let [0xff, marker] = *data.get(pos..pos + 2)? else {
+            return None;
+        };
+        match marker {
+            0x01 | 0xd0..=0xd7 => {
+                pos += 2; // no length field
+                continue;
+            }
+            0xd9 | 0xda => return None, // end of image, or scan data: no frame header before it
+            _ => {}
+        }
+        let segment = data.get(pos + 2..)?;
+        let len = usize::from(u16::from_be_bytes([*segment.first()?, *segment.get(1)?]));
+        // SOF0–SOF15, except DHT (c4), JPG (c8) and DAC (cc), which share the range
+        if matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+            let height = u16::from_be_bytes([*segment.get(3)?, *segment.get(4)?]);
+            let width = u16::from_be_bytes([*segment.get(5)?, *segment.get(6)?]);
+            // a height of 0 is given later in a DNL segment, which isn't worth chasing
+            return (width > 0 && height > 0).then_some((u32::from(width), u32::from(height)));
+        }
+        pos += 2 + len;
+    }
+}
+
+/// Read at most the first few bytes of `path` and identify it.
+pub fn sniff_file(path: &Path) -> io::Result<Option<ImageType>> {
+    let mut head = [0u8; SNIFF_LEN];
+    let mut len = 0;
+    let mut file = File::open(path)?;
+    while len < SNIFF_LEN {
+        match file.read(&mut head[len..])? {
+            0 => break, // file shorter than SNIFF_LEN
+            n => len += n,
+        }
+    }
+    Ok(sniff_bytes(&head[..len]))
+}
+
+/// Keep the files whose contents look like a supported image, checked in parallel.
+/// Unreadable files are dropped here; the regular extraction would fail on them anyway.
+pub fn sniff_images(files: Vec<PathBuf>) -> Vec<PathBuf> {
+    files
+        .into_par_iter()
+        .filter(|path| looks_like_image(path))
+        .collect()
+}
+
+/// Whether the file at `path` starts like a supported image; `false` when it
+/// can't be read.
+pub fn looks_like_image(path: &Path) -> bool {
+    matches!(sniff_file(path), Ok(Some(_)))
+}
@@ -0,0 +1,255 @@
+//! Tag identity: the names users type on the command line, and how they are matched.
+
+use std::collections::HashMap;
+
+use rexif::{ExifEntry, ExifTag, IfdFormat, IfdKind, TagValue};
+
+/// Names for common tags rexif reports as `UnknownToMe`, by numeric ID.
+const EXTRA_TAG_NAMES: &[(u16, &str)] = &[
+    (0x0100, "ImageWidth"),
+    (0x0101, "ImageLength"),
+    (0x013b, "Artist"),
+    (0x9010, "OffsetTime"),
+    (0x9011, "OffsetTimeOriginal"),
+    (0x9012, "OffsetTimeDigitized"),
+    (0x9290, "SubSecTime"),
+    (0x9291, "SubSecTimeOriginal"),
+    (0xa002, "PixelXDimension"),
+    (0xa003, "PixelYDimension"),
+    (0xa430, "CameraOwnerName"),
+    (0xa431, "BodySerialNumber"),
+    (0xa435, "LensSerialNumber"),
+];
+
+/// Every tag rexif has a name for, so names can be checked before any file is read.
+const REXIF_TAGS: [ExifTag; 101] = [
+    ExifTag::ImageDescription, ExifTag::Make, ExifTag::Model, ExifTag::Orientation,
+    ExifTag::XResolution, ExifTag::YResolution, ExifTag::ResolutionUnit, ExifTag::Software,
+    ExifTag::DateTime, ExifTag::HostComputer, ExifTag::WhitePoint, ExifTag::PrimaryChromaticities,
+    ExifTag::YCbCrCoefficients, ExifTag::ReferenceBlackWhite, ExifTag::Copyright,
+    ExifTag::ExifOffset, ExifTag::GPSOffset, ExifTag::ExposureTime, ExifTag::FNumber,
+    ExifTag::ExposureProgram, ExifTag::SpectralSensitivity, ExifTag::ISOSpeedRatings, ExifTag::OECF,
+    ExifTag::SensitivityType, ExifTag::ExifVersion, ExifTag::DateTimeOriginal,
+    ExifTag::DateTimeDigitized, ExifTag::ShutterSpeedValue, ExifTag::ApertureValue,
+    ExifTag::BrightnessValue, ExifTag::ExposureBiasValue, ExifTag::MaxApertureValue,
+    ExifTag::SubjectDistance, ExifTag::MeteringMode, ExifTag::LightSource, ExifTag::Flash,
+    ExifTag::FocalLength, ExifTag::SubjectArea, ExifTag::MakerNote, ExifTag::UserComment,
+    ExifTag::FlashPixVersion, ExifTag::ColorSpace, ExifTag::RelatedSoundFile, ExifTag::FlashEnergy,
+    ExifTag::FocalPlaneXResolution, ExifTag::FocalPlaneYResolution,
+    ExifTag::FocalPlaneResolutionUnit, ExifTag::SubjectLocation, ExifTag::ExposureIndex,
+    ExifTag::SensingMethod, ExifTag::FileSource, ExifTag::SceneType, ExifTag::CFAPattern,
+    ExifTag::CustomRendered, ExifTag::ExposureMode, ExifTag::WhiteBalanceMode,
+    ExifTag::DigitalZoomRatio, ExifTag::FocalLengthIn35mmFilm, ExifTag::SceneCaptureType,
+    ExifTag::GainControl, ExifTag::Contrast, ExifTag::Saturation, ExifTag::Sharpness,
+    ExifTag::DeviceSettingDescription, ExifTag::SubjectDistanceRange, ExifTag::ImageUniqueID,
+    ExifTag::LensSpecification, ExifTag::LensMake, ExifTag::LensModel, ExifTag::Gamma,
+    ExifTag::GPSVersionID, ExifTag::GPSLatitudeRef, ExifTag::GPSLatitude, ExifTag::GPSLongitudeRef,
+    ExifTag::GPSLongitude, ExifTag::GPSAltitudeRef, ExifTag::GPSAltitude, ExifTag::GPSTimeStamp,
+    ExifTag::GPSSatellites, ExifTag::GPSStatus, ExifTag::GPSMeasureMode, ExifTag::GPSDOP,
+    ExifTag::GPSSpeedRef, ExifTag::GPSSpeed, ExifTag::GPSTrackRef, ExifTag::GPSTrack,
+    ExifTag::GPSImgDirectionRef, ExifTag::GPSImgDirection, ExifTag::GPSMapDatum,
+    ExifTag::GPSDestLatitudeRef, ExifTag::GPSDestLatitude, ExifTag::GPSDestLongitudeRef,
+    ExifTag::GPSDestLongitude, ExifTag::GPSDestBearingRef, ExifTag::GPSDestBearing,
+    ExifTag::GPSDestDistanceRef, ExifTag::GPSDestDistance, ExifTag::GPSProcessingMethod,
+    ExifTag::GPSAreaInformation, ExifTag::GPSDateStamp, ExifTag::GPSDifferential,
+];
+
+/// Whether `wanted` names a tag this tool can recognise in a file: one of rexif's
+/// (by name or label), one of [`EXTRA_TAG_NAMES`], or any hex ID.
+pub fn is_known_tag(wanted: &str) -> bool {
+    if let Some(hex) = wanted.strip_prefix("0x").or_else(|| wanted.strip_prefix("0X")) {
+        return u16::from_str_radix(hex, 16).is_ok();
+    }
+    let wanted = normalize_tag_name(wanted);
+    let rexif = REXIF_TAGS
+        .iter()
+        .any(|tag| normalize_tag_name(&format!("{tag:?}")) == wanted || normalize_tag_name(&tag.to_string()) == wanted);
+    rexif || EXTRA_TAG_NAMES.iter().any(|(_, name)| normalize_tag_name(name) == wanted)
+}
+
+/// One EXIF entry as kept on a [`Row`](crate::Row).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tag {
+    pub id: u16,            // numeric tag ID
+    pub name: String,       // e.g. "DateTimeOriginal", or "0xc4a5" when unknown
+    pub label: String,      // rexif's description, e.g. "Date of original image"; the name if unknown
+    pub value: String,      // readable value
+    pub kind: &'static str, // EXIF data type as stored, e.g. "rational" (--raw-tags)
+    pub raw: String,        // unconverted value, see [`raw_value`] (--raw-tags)
+}
+
+impl Tag {
+    pub fn from_entry(entry: &ExifEntry) -> Tag {
+        let name = tag_name(entry);
+        // rexif labels every unknown tag alike and puts the ID into the value;
+        // the name says more, and the value is left plain
+        let (label, value) = match entry.tag {
+            ExifTag::UnknownToMe => (name.clone(), entry.value.to_string()),
+            _ => (entry.tag.to_string(), entry.value_more_readable.to_string()),
+        };
+        Tag {
+            id: entry.ifd.tag,
+            name,
+            label,
+            value,
+            kind: format_name(entry.ifd.format),
+            raw: raw_value(&entry.value),
+        }
+    }
+
+    /// The ID as written in `--raw-tags` output, e.g. `0x829a`.
+    pub fn hex_id(&self) -> String {
+        format!("0x{:04x}", self.id)
+    }
+
+    /// Whether this is the tag a user meant by `wanted` (see [`normalize_tag_name`]);
+    /// the name, the label and the hex ID (`0x9003`) all count.
+    pub fn matches(&self, wanted: &str) -> bool {
+        if let Some(hex) = wanted.strip_prefix("0x").or_else(|| wanted.strip_prefix("0X")) {
+            return u16::from_str_radix(hex, 16) == Ok(self.id);
+        }
+        let wanted = normalize_tag_name(wanted);
+        normalize_tag_name(&self.name) == wanted || normalize_tag_name(&self.label) == wanted
+    }
+}
+
+/// The TIFF name of an EXIF data type, in lower case.
+pub fn format_name(format: IfdFormat) -> &'static str {
+    match format {
+        IfdFormat::U8 => "byte",
+        IfdFormat::Ascii => "ascii",
+        IfdFormat::U16 => "short",
+        IfdFormat::U32 => "long",
+        IfdFormat::URational => "rational",
+        IfdFormat::I8 => "sbyte",
+        IfdFormat::Undefined => "undefined",
+        IfdFormat::I16 => "sshort",
+        IfdFormat::I32 => "slong",
+        IfdFormat::IRational => "srational",
+        IfdFormat::F32 => "float",
+        IfdFormat::F64 => "double",
+        IfdFormat::Unknown => "unknown",
+    }
+}
+
+/// A value as stored, without rexif's rounding and translations: numbers
+/// separated by spaces, rationals as `num/den`, text as is, and opaque bytes
+/// as hex.
+pub fn raw_value(value: &TagValue) -> String {
+    fn join<T: ToString>(values: &[T]) -> String {
+        values.iter().map(T::to_string).collect::<Vec<_>>().join(" ")
+    }
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+    match value {
+        TagValue::Ascii(s) => s.clone(),
+        TagValue::U8(v) => join(v),
+        TagValue::U16(v) => join(v),
+        TagValue::U32(v) => join(v),
+        TagValue::I8(v) => join(v),
+        TagValue::I16(v) => join(v),
+        TagValue::I32(v) => join(v),
+        TagValue::F32(v) => join(v),
+        TagValue::F64(v) => join(v),
+        TagValue::URational(v) => {
+            let v: Vec<String> = v.iter().map(|r| format!("{}/{}", r.numerator, r.denominator)).collect();
+            v.join(" ")
+        }
+        TagValue::IRational(v) => {
+            let v: Vec<String> = v.iter().map(|r| format!("{}/{}", r.numerator, r.denominator)).collect();
+            v.join(" ")
+        }
+        TagValue::Undefined(bytes, _) | TagValue::Unknown(bytes, _) | TagValue::Invalid(bytes, ..) => hex(bytes),
+    }
+}
+
+/// Canonical name of an entry: rexif's (`DateTimeOriginal`), one of
+/// [`EXTRA_TAG_NAMES`] for tags it doesn't know, or else the hex ID (`0xc4a5`).
+pub fn tag_name(entry: &ExifEntry) -> String {
+    if entry.tag != ExifTag::UnknownToMe {
+        return format!("{:?}", entry.tag);
+    }
+    // GPS tag IDs restart at 0, so the table only applies outside the GPS IFD
+    let extra = EXTRA_TAG_NAMES
+        .iter()
+        .find(|(id, _)| *id == entry.ifd.tag && entry.kind != IfdKind::Gps);
+    match extra {
+        Some((_, name)) => name.to_string(),
+        None => format!("0x{:04x}", entry.ifd.tag),
+    }
+}
+
+/// Key for comparing tag names typed by users: case, spaces, `_` and `-` are
+/// ignored, so `DateTimeOriginal`, `date_time_original` and `datetimeoriginal` agree.
+pub fn normalize_tag_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '_' | '-'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Drop repeated tags (cameras often write one in both IFD0 and the Exif IFD, and
+/// the thumbnail's IFD1 repeats several), keeping for each name the entry from
+/// `prefer` if there is one, else the first. The survivors stay in file order.
+pub fn dedupe_entries(entries: &[ExifEntry], prefer: Option<IfdKind>) -> Vec<&ExifEntry> {
+    let names: Vec<String> = entries.iter().map(tag_name).collect();
+    let mut keep: HashMap<&str, usize> = HashMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let kept = keep.entry(&names[i]).or_insert(i);
+        if Some(entry.kind) == prefer && Some(entries[*kept].kind) != prefer {
+            *kept = i;
+        }
+    }
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| keep[names[*i].as_str()] == *i)
+        .map(|(_, entry)| entry)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rexif::{ExifEntry, ExifTag, IfdKind, TagValue};
+
+    use super::dedupe_entries;
+    use crate::testutil::entry;
+
+    fn ascii(tag: ExifTag, kind: IfdKind, value: &str) -> ExifEntry {
+        entry(tag, kind, TagValue::Ascii(value.into()), value)
+    }
+
+    /// A Software tag written to both IFD0 and the Exif IFD, with a Model in between.
+    fn repeated_software() -> Vec<ExifEntry> {
+        vec![
+            ascii(ExifTag::Software, IfdKind::Ifd0, "ifd0 copy"),
+            ascii(ExifTag::Model, IfdKind::Ifd0, "Canon EOS R5"),
+            ascii(ExifTag::Software, IfdKind::Exif, "exif copy"),
+        ]
+    }
+
+    fn readable(kept: &[&ExifEntry]) -> Vec<String> {
+        kept.iter().map(|entry| entry.value_more_readable.to_string()).collect()
+    }
+
+    #[test]
+    fn keeps_the_first_copy_by_default() {
+        let entries = repeated_software();
+        assert_eq!(readable(&dedupe_entries(&entries, None)), ["ifd0 copy", "Canon EOS R5"]);
+    }
+
+    #[test]
+    fn prefer_ifd_keeps_that_copy_in_file_order() {
+        let entries = repeated_software();
+        let kept = dedupe_entries(&entries, Some(IfdKind::Exif));
+        assert_eq!(readable(&kept), ["Canon EOS R5", "exif copy"]);
+        assert_eq!(kept[1].kind, IfdKind::Exif);
+    }
+
+    #[test]
+    fn prefer_ifd_without_a_copy_there_keeps_the_first() {
+        let entries = repeated_software();
+        assert_eq!(readable(&dedupe_entries(&entries, Some(IfdKind::Gps))), ["ifd0 copy", "Canon EOS R5"]);
+    }
+}
@@ -0,0 +1,92 @@
+//! Helpers shared by the unit tests: EXIF entries built by hand, and scratch
+//! folders under the system temp dir.
+
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rexif::{ExifEntry, ExifTag, IfdEntry, IfdFormat, IfdKind, Namespace, TagValue};
+
+/// An entry as rexif would parse it; `readable` stands in for `value_more_readable`.
+pub fn entry(tag: ExifTag, kind: IfdKind, value: TagValue, readable: &str) -> ExifEntry {
+    let format = match value {
+        TagValue::Ascii(_) => IfdFormat::Ascii,
+        TagValue::U16(_) => IfdFormat::U16,
+        TagValue::U32(_) => IfdFormat::U32,
+        TagValue::URational(_) => IfdFormat::URational,
+        _ => IfdFormat::Undefined,
+    };
+    ExifEntry {
+        namespace: Namespace::Standard,
+        ifd: IfdEntry {
+            namespace: Namespace::Standard,
+            tag: tag as u16,
+            format,
+            count: 1,
+            data: Vec::new(),
+            ifd_data: Vec::new(),
+            ext_data: Vec::new(),
+            le: true,
+        },
+        tag,
+        value,
+        unit: Cow::Borrowed("none"),
+        value_more_readable: Cow::Owned(readable.to_string()),
+        kind,
+    }
+}
+
+/// The smallest JPEG rexif parses: an EXIF block holding only a Software tag.
+pub fn jpeg_with_software(software: &str) -> Vec<u8> {
+    let mut text = software.as_bytes().to_vec();
+    text.push(0);
+    let mut tiff = b"II*\0".to_vec();
+    tiff.extend(8u32.to_le_bytes()); // IFD0 right after the header
+    tiff.extend(1u16.to_le_bytes());
+    tiff.extend((ExifTag::Software as u16).to_le_bytes());
+    tiff.extend(2u16.to_le_bytes()); // ASCII
+    tiff.extend((text.len() as u32).to_le_bytes());
+    tiff.extend(26u32.to_le_bytes()); // the text follows the IFD
+    tiff.extend(0u32.to_le_bytes()); // no next IFD
+    tiff.extend(text);
+    let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe1];
+    jpeg.extend(((2 + 6 + tiff.len()) as u16).to_be_bytes());
+    jpeg.extend(b"Exif\0\0");
+    jpeg.extend(tiff);
+    jpeg.extend([0xff, 0xd9]);
+    jpeg
+}
+
+/// A fresh, empty folder for one test; removed when dropped.
+pub struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    pub fn new(name: &str) -> ScratchDir {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let unique = format!("exif_etl-{}-{}-{}", name, std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+        let dir = std::env::temp_dir().join(unique);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create scratch folder");
+        ScratchDir(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Write `contents` to `name` inside the folder and return its path.
+    pub fn file(&self, name: &str, contents: &[u8]) -> PathBuf {
+        let path = self.0.join(name);
+        fs::write(&path, contents).expect("write scratch file");
+        path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
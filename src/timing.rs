@@ -0,0 +1,97 @@
+//! `--timing` support: wall time per phase and per-file parse times.
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+
+/// Measurements of one run.
+#[derive(Debug, Default)]
+pub struct Timings {
+    phases: Vec<(&'static str, Duration)>, // in the order they ran
+    files: Vec<(PathBuf, Duration)>,       // parse time per file
+}
+
+impl Timings {
+    pub fn new() -> Timings {
+        Timings::default()
+    }
+
+    /// Record that the phase `name`, begun at `start`, is over.
+    pub fn phase_done(&mut self, name: &'static str, start: Instant) {
+        self.phases.push((name, start.elapsed()));
+    }
+
+    /// Record per-file parse times, e.g. collected inside a parallel extraction.
+    pub fn add_files(&mut self, files: impl IntoIterator<Item = (PathBuf, Duration)>) {
+        self.files.extend(files);
+    }
+
+    /// Parse time that `q` (0–1) of the files stayed within (nearest rank),
+    /// `None` when no file was parsed.
+    pub fn percentile(&self, q: f64) -> Option<Duration> {
+        let mut times: Vec<Duration> = self.files.iter().map(|(_, d)| *d).collect();
+        times.sort_unstable();
+        let rank = (q * times.len() as f64).ceil() as usize;
+        times.get(rank.clamp(1, times.len().max(1)) - 1).copied()
+    }
+
+    /// The `n` files that took longest to parse, slowest first.
+    pub fn slowest(&self, n: usize) -> Vec<&(PathBuf, Duration)> {
+        let mut files: Vec<&(PathBuf, Duration)> = self.files.iter().collect();
+        files.sort_by_key(|(_, d)| std::cmp::Reverse(*d));
+        files.truncate(n);
+        files
+    }
+
+    /// Human-readable breakdown for the terminal.
+    pub fn report(&self, slowest: usize) -> String {
+        let mut out = String::from("Timing:\n");
+        for (name, d) in &self.phases {
+            out.push_str(&format!("  {:<12}{:>10.1} ms\n", name, ms(*d)));
+        }
+        if let (Some(p50), Some(p95), Some(max)) = (self.percentile(0.5), self.percentile(0.95), self.percentile(1.0)) {
+            out.push_str(&format!(
+                "  parse time per file ({} files): p50 {:.2} ms, p95 {:.2} ms, max {:.2} ms\n",
+                self.files.len(),
+                ms(p50),
+                ms(p95),
+                ms(max)
+            ));
+            out.push_str("  slowest files:\n");
+            for (path, d) in self.slowest(slowest) {
+                out.push_str(&format!("    {:>10.2} ms  {}\n", ms(*d), path.display()));
+            }
+        }
+        out
+    }
+
+    /// The same numbers for `--timing-json`, all durations in seconds.
+    pub fn to_json(&self, slowest: usize) -> Value {
+        let secs = |d: Option<Duration>| d.map(|d| d.as_secs_f64());
+        json!({
+            "phases": self
+                .phases
+                .iter()
+                .map(|(name, d)| json!({ "name": name, "seconds": d.as_secs_f64() }))
+                .collect::<Vec<_>>(),
+            "files": self.files.len(),
+            "parse_seconds": {
+                "p50": secs(self.percentile(0.5)),
+                "p95": secs(self.percentile(0.95)),
+                "max": secs(self.percentile(1.0)),
+            },
+            "slowest": self
+                .slowest(slowest)
+                .iter()
+                .map(|(path, d)| json!({ "path": path.to_string_lossy(), "seconds": d.as_secs_f64() }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
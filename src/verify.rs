@@ -0,0 +1,278 @@
+//! `verify`: check a report against the files on disk as they are now.
+//!
+//! Each listed file is fingerprinted first: gone, a different `file_size` or
+//! `sha256` than the report says, or modified after the report's
+//! `csv_created_at`. Only files whose fingerprint moved are parsed again, to
+//! see whether their EXIF still matches the report's columns.
+
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    extract_exif_with, has_extension, is_symlink, report::Report, sniff::RAW_EXTENSIONS, walk::walk_files, Column, ExtractOptions, Sanitize,
+};
+
+/// Columns that describe a run rather than the file (where `--organize` put
+/// it, what `--touch` did, …) and the size, which is checked on its own; they
+/// are not compared after a file is parsed again.
+const NOT_COMPARED: [Column; 11] = [
+    Column::Path,
+    Column::FileSize,
+    Column::OrganizedPath,
+    Column::OrganizeAction,
+    Column::MissingTags,
+    Column::DateDriftSeconds,
+    Column::DateDrift,
+    Column::MtimeBefore,
+    Column::MtimeAfter,
+    Column::TouchAction,
+    Column::OffsetGuessed,
+];
+
+/// How a file differs from the report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Drift {
+    Missing, // listed, but not on disk any more
+    Changed, // size, hash or EXIF differ from the report
+    New,     // an image under the report's folder that it doesn't list
+}
+
+impl Drift {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Drift::Missing => "missing",
+            Drift::Changed => "changed",
+            Drift::New => "new",
+        }
+    }
+}
+
+/// One file that doesn't match the report.
+#[derive(Clone, Debug)]
+pub struct Discrepancy {
+    pub path: PathBuf, // as the report writes it
+    pub drift: Drift,
+    pub detail: String, // what differs, e.g. "file_size 351 -> 402"
+}
+
+/// Outcome of [`verify`].
+#[derive(Clone, Debug, Default)]
+pub struct Verification {
+    pub listed: usize,    // files in the report
+    pub reparsed: usize,  // of those, parsed again because their fingerprint moved
+    pub rewritten: usize, // modified after the report, but with the same EXIF and size
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl Verification {
+    /// How many discrepancies are of kind `drift`.
+    pub fn count(&self, drift: Drift) -> usize {
+        self.discrepancies.iter().filter(|d| d.drift == drift).count()
+    }
+}
+
+/// Check every file `report` lists against the state of disk, resolving its
+/// (relative) paths against `dir`, and look for images it doesn't list under
+/// `dir`, down to `max_depth` folder levels as a `--recursive` scan would.
+/// Needs the report's `path` column.
+pub fn verify(report: &Report, dir: &Path, max_depth: Option<usize>) -> Result<Verification, String> {
+    let path_column = report
+        .column(Column::Path.name())
+        .ok_or("the report has no path column, so there is nothing to check")?;
+    let size_column = report.column(Column::FileSize.name());
+    let hash_column = report.column("sha256");
+    let created_at = report.created_at.as_deref().and_then(|stamp| DateTime::parse_from_rfc3339(stamp).ok());
+    let compared: Vec<(usize, Column)> = report
+        .header
+        .iter()
+        .enumerate()
+        .filter_map(|(i, name)| Some((i, Column::from_name(name)?)))
+        .filter(|(_, column)| !NOT_COMPARED.contains(column))
+        .collect();
+
+    let mut result = Verification::default();
+    let mut listed = HashSet::new();
+    for row in &report.rows {
+        let Some(path) = report.cell(row, Some(path_column)) else { continue };
+        let path = PathBuf::from(path);
+        if !listed.insert(path.clone()) {
+            continue; // a --long report repeats the path on every line
+        }
+        result.listed += 1;
+        let on_disk = dir.join(&path);
+        let metadata = match fs::metadata(&on_disk) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                result.discrepancies.push(Discrepancy { path, drift: Drift::Missing, detail: String::new() });
+                continue;
+            }
+            Err(e) => {
+                let detail = format!("can't be read: {e}");
+                result.discrepancies.push(Discrepancy { path, drift: Drift::Changed, detail });
+                continue;
+            }
+        };
+        // cheap checks first; the hash only when the size still agrees
+        let mut differences = Vec::new();
+        if let Some(size) = report.cell(row, size_column) {
+            if size != metadata.len().to_string() {
+                differences.push(format!("file_size {} -> {}", size, metadata.len()));
+            }
+        }
+        if let Some(hash) = report.cell(row, hash_column).filter(|_| differences.is_empty()) {
+            match sha256(&on_disk) {
+                Ok(now) if now.eq_ignore_ascii_case(hash) => {}
+                Ok(now) => differences.push(format!("sha256 {hash} -> {now}")),
+                Err(e) => differences.push(format!("sha256 can't be computed: {e}")),
+            }
+        }
+        let modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+        let rewritten = match (created_at, modified) {
+            (Some(created), Some(modified)) => modified > created,
+            // without a stamp to compare against, every file is parsed again
+            _ => size_column.is_none() && hash_column.is_none(),
+        };
+        if differences.is_empty() && !rewritten {
+            continue;
+        }
+        result.reparsed += 1;
+        match extract_exif_with(&on_disk, &ExtractOptions::default()) {
+            Ok(mut now) => {
+                now.sanitize(Sanitize::default());
+                for &(i, column) in &compared {
+                    let before = row.get(i).map(String::as_str).unwrap_or_default();
+                    let cell = now.cell(column);
+                    if cell != before {
+                        differences.push(format!("{} {:?} -> {:?}", column.name(), before, cell));
+                    }
+                }
+            }
+            Err(e) => differences.push(format!("EXIF can't be read any more: {}: {}", e.category.as_str(), e.message)),
+        }
+        if differences.is_empty() {
+            result.rewritten += 1;
+        } else {
+            let detail = differences.join("; ");
+            result.discrepancies.push(Discrepancy { path, drift: Drift::Changed, detail });
+        }
+    }
+
+    // new files: images under `dir` that the report doesn't name, found (and
+    // named) as a scan from there finds them, links left out; RAW files only
+    // when it lists some, or a run without --raw finds them all new
+    let raw: &[&str] = if listed.iter().any(|path| has_extension(path, RAW_EXTENSIONS)) { RAW_EXTENSIONS } else { &[] };
+    let known: HashSet<PathBuf> = listed.iter().map(|path| plain(path)).collect();
+    // folders that can't be listed are passed over, as their files show up missing
+    let mut new: Vec<PathBuf> = walk_files(dir, max_depth, false)
+        .files
+        .into_iter()
+        .filter(|file| !is_symlink(file))
+        .filter_map(|file| Some(Path::new(".").join(file.strip_prefix(dir).ok()?)))
+        .filter(|path| has_extension(path, &["jpg", "jpeg"]) || has_extension(path, raw))
+        .filter(|path| !known.contains(&plain(path)))
+        .collect();
+    new.sort();
+    result.discrepancies.extend(new.into_iter().map(|path| Discrepancy { path, drift: Drift::New, detail: String::new() }));
+    Ok(result)
+}
+
+/// `path` without `.` components, so `./a.jpg` and `a.jpg` compare equal.
+fn plain(path: &Path) -> PathBuf {
+    path.components().filter(|c| *c != Component::CurDir).collect()
+}
+
+/// Lower-case hex SHA-256 of the file at `path`.
+fn sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{jpeg_with_software, ScratchDir};
+
+    const BEFORE_NOW: &str = "2000-01-01T00:00:00+00:00"; // every file was touched after it
+    const AFTER_NOW: &str = "2999-01-01T00:00:00+00:00"; // no file was
+
+    /// A report made `created_at` with `lines` under `header`.
+    fn report(created_at: &str, header: &str, lines: &[String]) -> Report {
+        let dir = ScratchDir::new("verify-report");
+        let text = format!("# csv_created_at: {created_at}; schema_version: 4\n{header}\n{}\n", lines.join("\n"));
+        Report::read(&dir.file("exif_output.csv", text.as_bytes())).unwrap()
+    }
+
+    fn drifts(result: &Verification) -> Vec<(&str, Drift)> {
+        result.discrepancies.iter().map(|d| (d.path.to_str().unwrap(), d.drift)).collect()
+    }
+
+    #[test]
+    fn a_listed_file_that_is_gone_is_missing() {
+        let photos = ScratchDir::new("verify-missing");
+        let result = verify(&report(AFTER_NOW, "path", &["./gone.jpg".into()]), photos.path(), None).unwrap();
+        assert_eq!(result.listed, 1);
+        assert_eq!(drifts(&result), [("./gone.jpg", Drift::Missing)]);
+    }
+
+    #[test]
+    fn a_changed_size_is_found_first_then_a_changed_hash() {
+        let photos = ScratchDir::new("verify-changed");
+        let jpeg = jpeg_with_software("Lightroom 6");
+        let size = photos.file("a.jpg", &jpeg).metadata().unwrap().len();
+        let header = "path,file_size,sha256,software";
+        let wrong_hash = "0".repeat(64);
+
+        // the size differs, so the hash isn't even computed
+        let line = format!("./a.jpg,{},{},Lightroom 6", size + 1, wrong_hash);
+        let result = verify(&report(AFTER_NOW, header, &[line]), photos.path(), None).unwrap();
+        assert_eq!(drifts(&result), [("./a.jpg", Drift::Changed)]);
+        assert_eq!(result.discrepancies[0].detail, format!("file_size {} -> {}", size + 1, size));
+
+        let line = format!("./a.jpg,{},{},Lightroom 6", size, wrong_hash);
+        let result = verify(&report(AFTER_NOW, header, &[line]), photos.path(), None).unwrap();
+        assert_eq!(drifts(&result), [("./a.jpg", Drift::Changed)]);
+        assert!(result.discrepancies[0].detail.starts_with(&format!("sha256 {wrong_hash} -> ")));
+        assert_eq!(result.reparsed, 1);
+    }
+
+    #[test]
+    fn unlisted_images_are_new_at_any_depth() {
+        let photos = ScratchDir::new("verify-new");
+        fs::create_dir_all(photos.path().join("sub").join("deeper")).unwrap();
+        photos.file("a.jpg", &jpeg_with_software("Lightroom 6"));
+        photos.file("sub/deeper/b.JPEG", &jpeg_with_software("Lightroom 6"));
+        photos.file("sub/notes.txt", b"not an image");
+        let lines = ["a.jpg".to_string()]; // listed without the ./ a scan writes
+        let result = verify(&report(AFTER_NOW, "path", &lines), photos.path(), None).unwrap();
+        assert_eq!(drifts(&result), [("./sub/deeper/b.JPEG", Drift::New)]);
+
+        let shallow = verify(&report(AFTER_NOW, "path", &lines), photos.path(), Some(1)).unwrap();
+        assert!(shallow.discrepancies.is_empty());
+    }
+
+    #[test]
+    fn a_file_rewritten_with_the_same_exif_counts_as_rewritten() {
+        let photos = ScratchDir::new("verify-rewritten");
+        let size = photos.file("a.jpg", &jpeg_with_software("Lightroom 6")).metadata().unwrap().len();
+        let header = "path,file_size,software";
+
+        let line = format!("./a.jpg,{size},Lightroom 6");
+        let result = verify(&report(BEFORE_NOW, header, &[line]), photos.path(), None).unwrap();
+        assert!(result.discrepancies.is_empty());
+        assert_eq!((result.reparsed, result.rewritten), (1, 1));
+
+        let line = format!("./a.jpg,{size},Photoshop");
+        let result = verify(&report(BEFORE_NOW, header, &[line]), photos.path(), None).unwrap();
+        assert_eq!(drifts(&result), [("./a.jpg", Drift::Changed)]);
+        assert_eq!(result.discrepancies[0].detail, r#"software "Photoshop" -> "Lightroom 6""#);
+        assert_eq!(result.rewritten, 0);
+    }
+}
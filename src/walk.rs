@@ -0,0 +1,187 @@
+//! `--recursive` support: walk a folder tree, reading sibling folders in parallel
+//! (or one at a time, lazily, for [`crate::extract_dir`]).
+
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Mutex},
+};
+
+use rayon::prelude::*;
+
+/// What a walk found.
+#[derive(Debug, Default)]
+pub struct Walk {
+    pub files: Vec<PathBuf>,                   // regular files and links to files, in no particular order
+    pub unreadable: Vec<(PathBuf, io::Error)>, // folders whose listing failed, and why; their subtrees are missing
+}
+
+/// Every file under `root`, down to `max_depth` folder levels below it (`None` =
+/// no limit). Linked folders are entered only with `follow_symlinks`, and then
+/// each real folder once, so a link back up the tree can't loop.
+pub fn walk_files(root: &Path, max_depth: Option<usize>, follow_symlinks: bool) -> Walk {
+    let files = Mutex::new(Vec::new());
+    let unreadable = Walker::new(max_depth, follow_symlinks).walk(root, 0, &|found| {
+        files.lock().unwrap_or_else(|e| e.into_inner()).extend(found);
+    });
+    Walk { files: files.into_inner().unwrap_or_else(|e| e.into_inner()), unreadable }
+}
+
+/// [`walk_files`], but each folder's files are sent down `found` as soon as
+/// the folder is listed, so they can be worked on while the walk goes on.
+/// The walk has threads of its own, as many as the global rayon pool: a
+/// consumer that ties up that pool waiting on the channel can't starve it.
+/// Returns, once the whole tree is read, the folders whose listing failed;
+/// `found` is dropped by then, so the receiving end sees the channel close.
+pub fn stream_files(root: &Path, max_depth: Option<usize>, found: Sender<PathBuf>) -> io::Result<Vec<(PathBuf, io::Error)>> {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(rayon::current_num_threads()).build().map_err(io::Error::other)?;
+    let walker = Walker::new(max_depth, false);
+    let send = |files: Vec<PathBuf>| {
+        for file in files {
+            let _ = found.send(file); // a receiver that hung up wants no more
+        }
+    };
+    Ok(pool.install(|| walker.walk(root, 0, &send)))
+}
+
+struct Walker {
+    max_depth: Option<usize>,
+    follow_symlinks: bool,
+    visited: Mutex<HashSet<PathBuf>>, // canonical folders already listed (with follow_symlinks)
+}
+
+impl Walker {
+    fn new(max_depth: Option<usize>, follow_symlinks: bool) -> Walker {
+        Walker {
+            max_depth,
+            follow_symlinks,
+            visited: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Hand the files of `dir` and the folders below it to `found`, one
+    /// folder's worth at a time; returns the folders that couldn't be listed.
+    fn walk(&self, dir: &Path, depth: usize, found: &(impl Fn(Vec<PathBuf>) + Sync)) -> Vec<(PathBuf, io::Error)> {
+        if self.follow_symlinks {
+            let first_visit = match fs::canonicalize(dir) {
+                Ok(real) => self.visited.lock().unwrap_or_else(|e| e.into_inner()).insert(real),
+                Err(_) => true, // read_dir below reports it
+            };
+            if !first_visit {
+                return Vec::new();
+            }
+        }
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => return vec![(dir.to_path_buf(), e)],
+        };
+        let (mut files, mut subdirs) = (Vec::new(), Vec::new());
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else { continue };
+            let path = entry.path();
+            if file_type.is_dir() {
+                subdirs.push(path);
+            } else if file_type.is_file() {
+                files.push(path);
+            } else if file_type.is_symlink() {
+                match fs::metadata(&path) {
+                    Ok(target) if target.is_file() => files.push(path),
+                    Ok(target) if target.is_dir() && self.follow_symlinks => subdirs.push(path),
+                    _ => {} // dangling link, or a folder we don't follow
+                }
+            }
+        }
+        found(files);
+        // the loop consumed the listing, so its handle is closed before going deeper
+        if self.max_depth.is_some_and(|max| depth >= max) {
+            return Vec::new();
+        }
+        subdirs.par_iter().flat_map_iter(|sub| self.walk(sub, depth + 1, found)).collect()
+    }
+}
+
+/// Every file under `root`, listed one folder at a time as the iterator is
+/// drained, for callers that want results before the whole tree is read. Only
+/// one listing is open at once; linked folders are not entered. A folder that
+/// can't be listed comes out as an error naming it, and the walk goes on.
+pub fn lazy_walk(root: &Path) -> LazyWalk {
+    LazyWalk {
+        pending: vec![root.to_path_buf()],
+        current: None,
+    }
+}
+
+/// The iterator returned by [`lazy_walk`].
+#[derive(Debug)]
+pub struct LazyWalk {
+    pending: Vec<PathBuf>,        // folders found but not listed yet
+    current: Option<fs::ReadDir>, // the listing being read
+}
+
+impl Iterator for LazyWalk {
+    type Item = Result<PathBuf, (PathBuf, io::Error)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(entries) = &mut self.current else {
+                let dir = self.pending.pop()?;
+                match fs::read_dir(&dir) {
+                    Ok(entries) => self.current = Some(entries),
+                    Err(e) => return Some(Err((dir, e))),
+                }
+                continue;
+            };
+            let Some(entry) = entries.next() else {
+                self.current = None;
+                continue;
+            };
+            let Ok(entry) = entry else { continue };
+            let Ok(file_type) = entry.file_type() else { continue };
+            let path = entry.path();
+            if file_type.is_dir() {
+                self.pending.push(path);
+            } else if file_type.is_file() || (file_type.is_symlink() && path.is_file()) {
+                return Some(Ok(path));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::ScratchDir;
+    use std::sync::mpsc;
+
+    #[test]
+    fn streamed_files_match_the_collected_walk() {
+        let dir = ScratchDir::new("walk-stream");
+        fs::create_dir_all(dir.path().join("a").join("b")).unwrap();
+        for name in ["top.jpg", "a/one.jpg", "a/b/two.jpg", "a/b/three.txt"] {
+            dir.file(name, b"");
+        }
+        let (sender, listed) = mpsc::channel();
+        let unreadable = stream_files(dir.path(), None, sender).unwrap();
+        assert!(unreadable.is_empty());
+        let mut streamed: Vec<PathBuf> = listed.iter().collect(); // ends: the walk dropped the sender
+        streamed.sort();
+        let mut collected = walk_files(dir.path(), None, false).files;
+        collected.sort();
+        assert_eq!(streamed.len(), 4);
+        assert_eq!(streamed, collected);
+
+        let (sender, listed) = mpsc::channel();
+        stream_files(dir.path(), Some(1), sender).unwrap();
+        assert_eq!(listed.iter().count(), 2, "top.jpg and a/one.jpg");
+    }
+
+    #[test]
+    fn a_missing_root_is_reported_not_streamed() {
+        let dir = ScratchDir::new("walk-missing");
+        let (sender, listed) = mpsc::channel();
+        let unreadable = stream_files(&dir.path().join("gone"), None, sender).unwrap();
+        assert_eq!(unreadable.len(), 1);
+        assert_eq!(listed.iter().count(), 0);
+    }
+}